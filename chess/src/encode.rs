@@ -0,0 +1,224 @@
+use crate::{Piece, Side, position::Position, square::Square};
+
+/// The number of bytes in a packed position
+pub const PACKED_SIZE: usize = 30;
+
+/// The maximum number of pieces a packed position can hold
+const MAX_PIECES: usize = 32;
+
+const OCCUPANCY: usize = 0;
+const PIECES: usize = 8;
+const FLAGS: usize = PIECES + MAX_PIECES / 2;
+const EP: usize = FLAGS + 1;
+const HALFMOVES: usize = EP + 1;
+const FULLMOVES: usize = HALFMOVES + 2;
+
+const NO_EP: u8 = 0xFF;
+
+static PIECE_CODES: [Piece; 12] = [
+    Piece::WP,
+    Piece::WN,
+    Piece::WB,
+    Piece::WR,
+    Piece::WQ,
+    Piece::WK,
+    Piece::BP,
+    Piece::BN,
+    Piece::BB,
+    Piece::BR,
+    Piece::BQ,
+    Piece::BK,
+];
+
+impl Position {
+    /// Pack the position into a fixed size binary encoding
+    ///
+    /// The layout is:
+    /// - 8 bytes: occupancy bitboard, little endian, bit `y * 8 + x` per square
+    /// - 16 bytes: one 4-bit piece code per occupied square, in square order
+    /// - 1 byte: side to move (bit 0) and castling permissions (bits 1-4)
+    /// - 1 byte: en passant square index, or 0xFF if there is none
+    /// - 2 bytes: halfmoves, little endian
+    /// - 2 bytes: fullmoves, little endian
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than 32 pieces, which `is_valid` rules out
+    #[must_use]
+    pub fn encode(&self) -> [u8; PACKED_SIZE] {
+        let mut bytes = [0u8; PACKED_SIZE];
+        let mut occupancy = 0u64;
+        let mut count = 0;
+
+        // Pieces
+        for idx in 0..64u8 {
+            let Some(piece) = self.get_side_piece_on(Square::from_index(idx)) else {
+                continue;
+            };
+
+            assert!(count < MAX_PIECES, "Too many pieces to encode");
+
+            occupancy |= 1 << idx;
+            bytes[PIECES + count / 2] |= (piece as u8) << (4 * (count % 2));
+            count += 1;
+        }
+        bytes[OCCUPANCY..PIECES].copy_from_slice(&occupancy.to_le_bytes());
+
+        // Side to move and castling permissions
+        bytes[FLAGS] = self.turn as u8;
+        for (i, allowed) in self.castling.iter().enumerate() {
+            if *allowed {
+                bytes[FLAGS] |= 1 << (i + 1);
+            }
+        }
+
+        // En passant
+        bytes[EP] = match self.ep {
+            Some(sq) => sq.y * 8 + sq.x,
+            None => NO_EP,
+        };
+
+        // Move counters
+        bytes[HALFMOVES..FULLMOVES].copy_from_slice(&self.halfmoves.to_le_bytes());
+        bytes[FULLMOVES..PACKED_SIZE].copy_from_slice(&self.fullmoves.to_le_bytes());
+
+        bytes
+    }
+
+    /// Unpack a position from the encoding produced by `encode`
+    /// Returns None if the bytes don't hold a valid position
+    #[must_use]
+    pub fn decode(bytes: &[u8; PACKED_SIZE]) -> Option<Self> {
+        let mut pos = Position::default();
+        let occupancy = u64::from_le_bytes(bytes[OCCUPANCY..PIECES].try_into().unwrap());
+        if occupancy.count_ones() as usize > MAX_PIECES {
+            return None;
+        }
+        let mut count = 0;
+
+        // Pieces
+        for idx in 0..64u8 {
+            if occupancy & (1 << idx) == 0 {
+                continue;
+            }
+
+            let code = (bytes[PIECES + count / 2] >> (4 * (count % 2))) & 0xF;
+            let piece = *PIECE_CODES.get(code as usize)?;
+            let sq = Square::from_index(idx);
+
+            pos.set_piece(piece, sq);
+            if piece == Piece::WK {
+                pos.ksq[Side::White as usize] = Some(sq);
+            }
+            if piece == Piece::BK {
+                pos.ksq[Side::Black as usize] = Some(sq);
+            }
+            count += 1;
+        }
+
+        // Side to move and castling permissions
+        pos.turn = if bytes[FLAGS] & 1 == 0 {
+            Side::White
+        } else {
+            Side::Black
+        };
+        for (i, allowed) in pos.castling.iter_mut().enumerate() {
+            *allowed = bytes[FLAGS] & (1 << (i + 1)) != 0;
+        }
+
+        // En passant
+        pos.ep = match bytes[EP] {
+            NO_EP => None,
+            idx @ 0..64 => Some(Square::from_index(idx)),
+            _ => return None,
+        };
+
+        // Move counters
+        pos.halfmoves = u16::from_le_bytes([bytes[HALFMOVES], bytes[HALFMOVES + 1]]);
+        pos.fullmoves = u16::from_le_bytes([bytes[FULLMOVES], bytes[FULLMOVES + 1]]);
+
+        pos.refresh_key();
+        pos.is_valid().then_some(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perft::tests::TESTS;
+
+    fn assert_round_trip(pos: &Position) {
+        let decoded = Position::decode(&pos.encode()).expect("Failed to decode");
+        assert_eq!(decoded, *pos);
+        assert_eq!(decoded.ksq, pos.ksq);
+        assert_eq!(decoded.get_fen(), pos.get_fen());
+    }
+
+    #[test]
+    fn test_round_trip_perft() {
+        for (fen, _) in TESTS {
+            let pos = Position::from_fen(fen);
+            assert_round_trip(&pos);
+
+            // Also cover the positions one move later, which set EP squares
+            for mv in pos.pseudolegal_moves() {
+                let mut npos = pos;
+                if npos.makemove(&mv) {
+                    assert_round_trip(&npos);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_counters() {
+        let pos = Position::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 1234 4321");
+        assert_round_trip(&pos);
+    }
+
+    #[test]
+    fn test_full_board() {
+        let pos = Position::from_fen("startpos");
+        let bytes = pos.encode();
+
+        assert_eq!(
+            bytes[OCCUPANCY..PIECES],
+            0xFFFF_0000_0000_FFFFu64.to_le_bytes()
+        );
+        assert_eq!(bytes[FLAGS], 0b11110);
+        assert_eq!(bytes[EP], NO_EP);
+        assert_round_trip(&pos);
+    }
+
+    #[test]
+    fn test_corrupt() {
+        let pos = Position::from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1");
+        let bytes = pos.encode();
+
+        // Unused piece codes
+        let mut corrupt = bytes;
+        corrupt[PIECES] |= 0xF;
+        assert_eq!(Position::decode(&corrupt), None);
+
+        // En passant squares off the board or on the wrong rank
+        for ep in [64, 200, 4, 44, 19] {
+            let mut corrupt = bytes;
+            corrupt[EP] = ep;
+            assert_eq!(Position::decode(&corrupt), None, "{ep}");
+        }
+
+        // More pieces than the encoding has room for
+        let mut corrupt = bytes;
+        corrupt[OCCUPANCY..PIECES].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert_eq!(Position::decode(&corrupt), None);
+
+        // A missing king
+        let mut corrupt = bytes;
+        corrupt[OCCUPANCY] &= !(1 << 4);
+        assert_eq!(Position::decode(&corrupt), None);
+
+        // Garbage
+        assert_eq!(Position::decode(&[0xAB; PACKED_SIZE]), None);
+        assert_eq!(Position::decode(&[0; PACKED_SIZE]), None);
+    }
+}
@@ -9,6 +9,81 @@ impl Position {
         pos
     }
 
+    /// Create a new position from the FEN given, if it's valid
+    #[must_use]
+    pub fn try_from_fen(fen: &str) -> Option<Self> {
+        if Self::is_valid_fen(fen) {
+            Some(Self::from_fen(fen))
+        } else {
+            None
+        }
+    }
+
+    /// Is the string a FEN that `set_fen` will accept?
+    #[must_use]
+    pub fn is_valid_fen(fen: &str) -> bool {
+        if fen == "startpos" {
+            return true;
+        }
+
+        let parts = fen.split(" ").collect::<Vec<&str>>();
+        if parts.len() != 6 {
+            return false;
+        }
+
+        // Pieces
+        let ranks = parts[0].split("/").collect::<Vec<&str>>();
+        if ranks.len() != 8 {
+            return false;
+        }
+        for rank in ranks {
+            let mut width = 0;
+            for c in rank.chars() {
+                match c {
+                    'P' | 'N' | 'B' | 'R' | 'Q' | 'K' | 'p' | 'n' | 'b' | 'r' | 'q' | 'k' => {
+                        width += 1
+                    }
+                    '1'..='8' => width += c as u32 - '0' as u32,
+                    _ => return false,
+                }
+            }
+            if width != 8 {
+                return false;
+            }
+        }
+
+        // Kings
+        if parts[0].matches('K').count() != 1 || parts[0].matches('k').count() != 1 {
+            return false;
+        }
+
+        // Side to move
+        if parts[1] != "w" && parts[1] != "b" {
+            return false;
+        }
+
+        // Castling permissions
+        if parts[2] != "-" && !parts[2].chars().all(|c| "KQkq".contains(c)) {
+            return false;
+        }
+
+        // En passant
+        let ep = parts[3].as_bytes();
+        if parts[3] != "-"
+            && !(ep.len() == 2 && (b'a'..=b'h').contains(&ep[0]) && (b'1'..=b'8').contains(&ep[1]))
+        {
+            return false;
+        }
+
+        // Move counters
+        if parts[4].parse::<u16>().is_err() || parts[5].parse::<u16>().is_err() {
+            return false;
+        }
+
+        // The position itself
+        Self::from_fen(fen).is_valid()
+    }
+
     /// Set the position to the FEN given
     pub fn set_fen(&mut self, fen: &str) {
         if fen == "startpos" {
@@ -115,15 +190,44 @@ impl Position {
         }
 
         // Halfmoves
-        self.halfmoves = parts[4].parse::<u8>().unwrap();
+        self.halfmoves = parts[4].parse::<u16>().unwrap();
 
         // Fullmoves
-        self.fullmoves = parts[5].parse::<u8>().unwrap();
+        self.fullmoves = parts[5].parse::<u16>().unwrap();
+
+        self.refresh_key();
     }
 
     /// Get the FEN of the position
     #[must_use]
     pub fn get_fen(&self) -> String {
+        self.build_fen(false)
+    }
+
+    /// Get the FEN of the position, only including the en passant square if
+    /// an en passant capture is legal. This matches what X-FEN and PGN tools expect.
+    #[must_use]
+    pub fn get_fen_legal_ep(&self) -> String {
+        self.build_fen(true)
+    }
+
+    /// Is there a legal en passant capture available?
+    #[must_use]
+    pub fn has_legal_ep(&self) -> bool {
+        let Some(ep) = self.ep else {
+            return false;
+        };
+
+        self.pseudolegal_moves().iter().any(|mv| {
+            let piece = self.get_side_piece_on(mv.from);
+            let mut npos = *self;
+            mv.to == ep
+                && (piece == Some(Piece::WP) || piece == Some(Piece::BP))
+                && npos.makemove(mv)
+        })
+    }
+
+    fn build_fen(&self, legal_ep_only: bool) -> String {
         let mut fen = String::new();
 
         // Pieces
@@ -194,7 +298,9 @@ impl Position {
         }
 
         // En passant
-        if let Some(sq) = &self.ep {
+        if let Some(sq) = &self.ep
+            && (!legal_ep_only || self.has_legal_ep())
+        {
             fen += " ";
             fen += &sq.to_string();
         } else {
@@ -216,6 +322,7 @@ impl Position {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mv::Mv;
 
     static FENS_VALID: [&str; 11] = [
         "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
@@ -248,6 +355,184 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_is_valid_fen() {
+        for fen in FENS_VALID {
+            assert!(Position::is_valid_fen(fen), "{fen}");
+            assert_eq!(Position::try_from_fen(fen), Some(Position::from_fen(fen)));
+        }
+        assert!(Position::is_valid_fen("startpos"));
+
+        let invalid = [
+            "",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1BNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkx - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq z9 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - a 1",
+            // Pawns on the first or last rank
+            "P3k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/p3K3 b - - 0 1",
+            // En passant squares that can't follow a double pawn push
+            "4k3/8/8/3pP3/8/8/8/4K3 w - e8 0 1",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d3 0 1",
+            "4k3/8/8/3pP3/8/8/8/4K3 b - d6 0 1",
+            "4k3/8/8/4P3/8/8/8/4K3 w - d6 0 1",
+            // The side not to move is in check
+            "4k3/8/8/8/8/8/8/K3R3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/K3r3 b - - 0 1",
+            // Castling permissions without the king and rook on their starting squares
+            "4k3/8/8/8/8/8/8/4K3 w K - 0 1",
+            "4k3/8/8/8/8/8/8/R3K3 w KQ - 0 1",
+            "r3k3/8/8/8/8/8/8/4K3 b k - 0 1",
+            "r6r/4k3/8/8/8/8/8/4K3 b kq - 0 1",
+            // More than 16 pieces for one side
+            "4k3/8/8/8/8/N7/PPPPPPPP/RNBQKBNR w - - 0 1",
+        ];
+        for fen in invalid {
+            assert!(!Position::is_valid_fen(fen), "{fen}");
+            assert_eq!(Position::try_from_fen(fen), None);
+        }
+    }
+
+    #[test]
+    fn test_large_counters() {
+        let fen = "4k3/8/8/8/8/8/8/4K3 w - - 0 300";
+        assert_eq!(Position::from_fen(fen).get_fen(), fen);
+
+        let fen = "4k3/8/8/8/8/8/8/4K3 b - - 1000 1200";
+        assert_eq!(Position::from_fen(fen).get_fen(), fen);
+    }
+
+    #[test]
+    fn test_legal_ep() {
+        let tests = [
+            // No pawn can capture
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+            ),
+            // Capture available
+            (
+                "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            ),
+            // Capture would expose the king
+            (
+                "4k3/8/8/K2pP2r/8/8/8/8 w - d6 0 1",
+                "4k3/8/8/K2pP2r/8/8/8/8 w - - 0 1",
+            ),
+            // No EP square
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+                "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            ),
+        ];
+
+        for (fen, expected) in tests {
+            let pos = Position::from_fen(fen);
+            assert_eq!(pos.get_fen(), fen);
+            assert_eq!(pos.get_fen_legal_ep(), expected);
+        }
+    }
+
+    fn play_game(start: &str, moves: &[&str]) -> Position {
+        let mut pos = Position::from_fen(start);
+        for movestr in moves {
+            let success = pos.makemove(&Mv::from_string(movestr));
+            assert!(success, "Illegal move {movestr}");
+
+            let fen = pos.get_fen();
+            assert_eq!(Position::from_fen(&fen).get_fen(), fen);
+            let fen = pos.get_fen_legal_ep();
+            assert_eq!(Position::from_fen(&fen).get_fen(), fen);
+        }
+        pos
+    }
+
+    #[test]
+    fn test_game_immortal() {
+        let moves = [
+            "e2e4", "e7e5", "f2f4", "e5f4", "f1c4", "d8h4", "e1f1", "b7b5", "c4b5", "g8f6", "g1f3",
+            "h4h6", "d2d3", "f6h5", "f3h4", "h6g5", "h4f5", "c7c6", "g2g4", "h5f6", "h1g1", "c6b5",
+            "h2h4", "g5g6", "h4h5", "g6g5", "d1f3", "f6g8", "c1f4", "g5f6", "b1c3", "f8c5", "c3d5",
+            "f6b2", "f4d6", "c5g1", "e4e5", "b2a1", "f1e2", "b8a6", "f5g7", "e8d8", "f3f6", "g8f6",
+            "d6e7",
+        ];
+
+        let pos = play_game("startpos", &moves);
+        assert_eq!(
+            pos.get_fen(),
+            "r1bk3r/p2pBpNp/n4n2/1p1NP2P/6P1/3P4/P1P1K3/q5b1 b - - 1 23"
+        );
+    }
+
+    #[test]
+    fn test_game_of_the_century() {
+        // D. Byrne vs R. Fischer, New York 1956
+        let moves = [
+            "g1f3", "g8f6", "c2c4", "g7g6", "b1c3", "f8g7", "d2d4", "e8g8", "c1f4", "d7d5", "d1b3",
+            "d5c4", "b3c4", "c7c6", "e2e4", "b8d7", "a1d1", "d7b6", "c4c5", "c8g4", "f4g5", "b6a4",
+            "c5a3", "a4c3", "b2c3", "f6e4", "g5e7", "d8b6", "f1c4", "e4c3", "e7c5", "f8e8", "e1f1",
+            "g4e6", "c5b6", "e6c4", "f1g1", "c3e2", "g1f1", "e2d4", "f1g1", "d4e2", "g1f1", "e2c3",
+            "f1g1", "a7b6", "a3b4", "a8a4", "b4b6", "c3d1", "h2h3", "a4a2", "g1h2", "d1f2", "h1e1",
+            "e8e1", "b6d8", "g7f8", "f3e1", "c4d5", "e1f3", "f2e4", "d8b8", "b7b5", "h3h4", "h7h5",
+            "f3e5", "g8g7", "h2g1", "f8c5", "g1f1", "e4g3", "f1e1", "c5b4", "e1d1", "d5b3", "d1c1",
+            "g3e2", "c1b1", "e2c3", "b1c1", "a2c2",
+        ];
+
+        let pos = play_game("startpos", &moves[..26]);
+        assert_eq!(
+            pos.get_fen(),
+            "r2q1rk1/pp2ppbp/2p3p1/6B1/3Pn1b1/Q1P2N2/P4PPP/3RKB1R w K - 0 14"
+        );
+
+        // Sixteen plies without a capture or pawn move after 33...h5
+        let pos = play_game("startpos", &moves);
+        assert_eq!(
+            pos.get_fen(),
+            "1Q6/5pk1/2p3p1/1p2N2p/1b5P/1bn5/2r3P1/2K5 w - - 16 42"
+        );
+    }
+
+    #[test]
+    fn test_game_kasparov_topalov() {
+        // G. Kasparov vs V. Topalov, Wijk aan Zee 1999, with castling on opposite sides
+        let moves = [
+            "e2e4", "d7d6", "d2d4", "g8f6", "b1c3", "g7g6", "c1e3", "f8g7", "d1d2", "c7c6", "f2f3",
+            "b7b5", "g1e2", "b8d7", "e3h6", "g7h6", "d2h6", "c8b7", "a2a3", "e7e5", "e1c1", "d8e7",
+            "c1b1", "a7a6", "e2c1", "e8c8", "c1b3", "e5d4", "d1d4", "c6c5", "d4d1", "d7b6", "g2g3",
+            "c8b8", "b3a5", "b7a8", "f1h3", "d6d5", "h6f4", "b8a7", "h1e1", "d5d4", "c3d5", "b6d5",
+            "e4d5", "e7d6", "d1d4", "c5d4", "e1e7", "a7b6", "f4d4", "b6a5", "b2b4", "a5a4", "d4c3",
+            "d6d5", "e7a7", "a8b7", "a7b7", "d5c4", "c3f6", "a4a3", "f6a6", "a3b4", "c2c3", "b4c3",
+            "a6a1", "c3d2", "a1b2", "d2d1", "h3f1", "d8d2", "b7d7", "d2d7", "f1c4", "b5c4", "b2h8",
+            "d7d3", "h8a8", "c4c3", "a8a4", "d1e1", "f3f4", "f7f5", "b1c1", "d3d2", "a4a7",
+        ];
+
+        // Both sides have castled queenside
+        let pos = play_game("startpos", &moves[..26]);
+        assert_eq!(
+            pos.get_fen(),
+            "2kr3r/1b1nqp1p/p1pp1npQ/1p2p3/3PP3/P1N2P2/1PP3PP/1KNR1B1R w - - 2 14"
+        );
+
+        let pos = play_game("startpos", &moves);
+        assert_eq!(
+            pos.get_fen(),
+            "8/Q6p/6p1/5p2/5P2/2p3P1/3r3P/2K1k3 b - - 3 44"
+        );
+    }
+
+    #[test]
+    fn test_counter_overflow() {
+        // The counters stop at their largest value rather than wrapping
+        let pos = play_game("4k3/8/8/8/8/8/8/4K3 b - - 65535 65535", &["e8d8", "e1d1"]);
+        assert_eq!(pos.get_fen(), "3k4/8/8/8/8/8/8/3K4 b - - 65535 65535");
+    }
+
     #[test]
     fn test_fen_override() {
         let mut pos = Position::default();
@@ -0,0 +1,127 @@
+use crate::{mv::Mv, position::Position};
+
+impl Position {
+    /// Find the legal move that turns this position into the target position
+    /// The move counters are ignored, as is an en passant square with no legal capture
+    #[must_use]
+    pub fn find_move_to(&self, target: &Position) -> Option<Mv> {
+        self.legal_moves().into_iter().find(|mv| {
+            let mut npos = *self;
+            let _ = npos.makemove(mv);
+            npos.eq_ignoring_counters(target)
+        })
+    }
+
+    /// Find the legal move that results in the target's piece placement
+    /// Only the board is compared, so this works with externally observed boards
+    /// that don't know the side to move, castling permissions or en passant square
+    #[must_use]
+    pub fn find_move_to_fuzzy(&self, target: &Position) -> Option<Mv> {
+        self.legal_moves().into_iter().find(|mv| {
+            let mut npos = *self;
+            let _ = npos.makemove(mv);
+            npos.board == target.board
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_move_to() {
+        let tests = [
+            // Quiet move
+            (
+                "startpos",
+                "rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1",
+                "g1f3",
+            ),
+            // Double push, with and without the EP square
+            (
+                "startpos",
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                "e2e4",
+            ),
+            (
+                "startpos",
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+                "e2e4",
+            ),
+            // Castling
+            (
+                "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+                "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1",
+                "e1g1",
+            ),
+            (
+                "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1",
+                "2kr3r/8/8/8/8/8/8/R3K2R w KQ - 1 2",
+                "e8c8",
+            ),
+            // En passant
+            (
+                "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1",
+                "4k3/8/3P4/8/8/8/8/4K3 b - - 0 1",
+                "e5d6",
+            ),
+            // Promotions
+            (
+                "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N w - - 0 1",
+                "n1N5/P1Pk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+                "b7c8n",
+            ),
+            (
+                "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N w - - 0 1",
+                "n1Q5/P1Pk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+                "b7c8q",
+            ),
+        ];
+
+        for (before, after, movestr) in tests {
+            let pos = Position::from_fen(before);
+            let target = Position::from_fen(after);
+            let expected = Some(Mv::from_string(movestr));
+            assert_eq!(pos.find_move_to(&target), expected, "{before} {after}");
+            assert_eq!(
+                pos.find_move_to_fuzzy(&target),
+                expected,
+                "{before} {after}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_move_to_fuzzy() {
+        let pos = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+
+        // Wrong side to move and castling permissions
+        let target = Position::from_fen("r3k2r/8/8/8/8/8/8/R4RK1 w - - 0 1");
+        assert_eq!(pos.find_move_to(&target), None);
+        assert_eq!(
+            pos.find_move_to_fuzzy(&target),
+            Some(Mv::from_string("e1g1"))
+        );
+
+        // No move reaches the target
+        let target = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1");
+        assert_eq!(pos.find_move_to(&target), None);
+        assert_eq!(pos.find_move_to_fuzzy(&target), None);
+
+        // Illegal move
+        let pos = Position::from_fen("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1");
+        let target = Position::from_fen("4k3/8/8/8/8/8/4r3/3K4 b - - 1 1");
+        assert_eq!(
+            pos.find_move_to_fuzzy(&target),
+            Some(Mv::from_string("e1d1"))
+        );
+        let target = Position::from_fen("4k3/8/8/8/8/8/4r3/5K2 b - - 1 1");
+        assert_eq!(
+            pos.find_move_to_fuzzy(&target),
+            Some(Mv::from_string("e1f1"))
+        );
+        let target = Position::from_fen("4k3/8/8/8/8/8/3Kr3/8 b - - 1 1");
+        assert_eq!(pos.find_move_to_fuzzy(&target), None);
+    }
+}
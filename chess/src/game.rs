@@ -0,0 +1,264 @@
+use crate::{mv::Mv, position::Position};
+use std::fmt::Display;
+
+/// The version of the binary game format, bumped whenever the layout changes
+const FORMAT_VERSION: u8 = 1;
+
+/// The result of a game
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum GameResult {
+    Unknown,
+    WhiteWin,
+    BlackWin,
+    Draw,
+}
+
+/// A game, stored as a starting position and the moves played from it
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct Game {
+    pub start: Position,
+    pub moves: Vec<Mv>,
+    pub result: GameResult,
+}
+
+impl Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameResult::Unknown => write!(f, "*"),
+            GameResult::WhiteWin => write!(f, "1-0"),
+            GameResult::BlackWin => write!(f, "0-1"),
+            GameResult::Draw => write!(f, "1/2-1/2"),
+        }
+    }
+}
+
+impl Game {
+    /// Create a game with no moves from the given position
+    #[must_use]
+    pub fn new(start: Position) -> Self {
+        Self {
+            start,
+            moves: vec![],
+            result: GameResult::Unknown,
+        }
+    }
+
+    /// Get the position at the end of the game
+    #[must_use]
+    pub fn end(&self) -> Position {
+        let mut pos = self.start;
+        for mv in &self.moves {
+            let success = pos.makemove(mv);
+            assert!(success, "Illegal move {mv} in game");
+        }
+        pos
+    }
+
+    /// Encode the game into a compact binary format
+    ///
+    /// The layout is:
+    /// - 1 byte: the format version
+    /// - 1 byte: the result
+    /// - 2 bytes: the length of the starting FEN, little endian, 0 for the start position
+    /// - the starting FEN
+    /// - 1 byte per move: its index in `Position::legal_moves`
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![FORMAT_VERSION];
+
+        // Result
+        bytes.push(match self.result {
+            GameResult::Unknown => 0,
+            GameResult::WhiteWin => 1,
+            GameResult::BlackWin => 2,
+            GameResult::Draw => 3,
+        });
+
+        // Starting position
+        let fen = if self.start == Position::from_fen("startpos") {
+            String::new()
+        } else {
+            self.start.get_fen()
+        };
+        let len = u16::try_from(fen.len()).expect("FEN too long");
+        bytes.extend(len.to_le_bytes());
+        bytes.extend(fen.bytes());
+
+        // Moves
+        let mut pos = self.start;
+        for mv in &self.moves {
+            let idx = pos
+                .legal_moves()
+                .iter()
+                .position(|legal| legal == mv)
+                .unwrap_or_else(|| panic!("Illegal move {mv} in game"));
+            bytes.push(idx as u8);
+
+            let success = pos.makemove(mv);
+            debug_assert!(success);
+        }
+
+        bytes
+    }
+
+    /// Decode a game from the format produced by `encode`
+    /// Returns None if the bytes are truncated, from another version or hold an illegal game
+    #[must_use]
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let (&version, bytes) = bytes.split_first()?;
+        if version != FORMAT_VERSION {
+            return None;
+        }
+
+        // Result
+        let result = match bytes.first()? {
+            0 => GameResult::Unknown,
+            1 => GameResult::WhiteWin,
+            2 => GameResult::BlackWin,
+            3 => GameResult::Draw,
+            _ => return None,
+        };
+
+        // Starting position
+        let len = bytes.get(1..3)?;
+        let len = u16::from_le_bytes([len[0], len[1]]) as usize;
+        let fen = bytes.get(3..3 + len)?;
+        let start = if len == 0 {
+            Position::from_fen("startpos")
+        } else {
+            Position::try_from_fen(std::str::from_utf8(fen).ok()?)?
+        };
+
+        // Moves
+        let mut game = Game::new(start);
+        game.result = result;
+
+        let mut pos = start;
+        for idx in &bytes[3 + len..] {
+            let mv = *pos.legal_moves().get(*idx as usize)?;
+            let success = pos.makemove(&mv);
+            debug_assert!(success);
+            game.moves.push(mv);
+        }
+
+        Some(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game_from_strings(fen: &str, moves: &[&str], result: GameResult) -> Game {
+        let mut game = Game::new(Position::from_fen(fen));
+        game.moves = moves
+            .iter()
+            .map(|movestr| Mv::from_string(movestr))
+            .collect();
+        game.result = result;
+        game
+    }
+
+    #[test]
+    fn test_round_trip_startpos() {
+        let moves = [
+            "e2e4", "e7e5", "f2f4", "e5f4", "f1c4", "d8h4", "e1f1", "b7b5", "c4b5", "g8f6", "g1f3",
+            "h4h6", "d2d3", "f6h5", "f3h4", "h6g5", "h4f5", "c7c6", "g2g4", "h5f6", "h1g1", "c6b5",
+            "h2h4", "g5g6", "h4h5", "g6g5", "d1f3", "f6g8", "c1f4", "g5f6", "b1c3", "f8c5", "c3d5",
+            "f6b2", "f4d6", "c5g1", "e4e5", "b2a1", "f1e2", "b8a6", "f5g7", "e8d8", "f3f6", "g8f6",
+            "d6e7",
+        ];
+        let game = game_from_strings("startpos", &moves, GameResult::WhiteWin);
+        let bytes = game.encode();
+
+        assert_eq!(bytes.len(), 4 + moves.len());
+        assert_eq!(Game::decode(&bytes), Some(game.clone()));
+        assert_eq!(
+            game.end().get_fen(),
+            "r1bk3r/p2pBpNp/n4n2/1p1NP2P/6P1/3P4/P1P1K3/q5b1 b - - 1 23"
+        );
+    }
+
+    #[test]
+    fn test_round_trip_fen() {
+        let fen = "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1";
+        let moves = ["g2f1q", "e2f1", "d7c7", "b7c8q", "c7c8", "f1g2"];
+        let game = game_from_strings(fen, &moves, GameResult::Draw);
+        let bytes = game.encode();
+
+        assert_eq!(bytes.len(), 4 + fen.len() + moves.len());
+        assert_eq!(Game::decode(&bytes), Some(game));
+    }
+
+    #[test]
+    fn test_empty() {
+        for result in [
+            GameResult::Unknown,
+            GameResult::WhiteWin,
+            GameResult::BlackWin,
+            GameResult::Draw,
+        ] {
+            let mut game = Game::new(Position::from_fen("startpos"));
+            game.result = result;
+            assert_eq!(Game::decode(&game.encode()), Some(game));
+        }
+    }
+
+    #[test]
+    fn test_truncated() {
+        let fen = "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1";
+        let game = game_from_strings(fen, &["g2f1q", "e2f1"], GameResult::Draw);
+        let bytes = game.encode();
+
+        // Cutting off moves leaves a shorter game, anything else is rejected
+        for len in 0..4 + fen.len() {
+            assert_eq!(Game::decode(&bytes[..len]), None, "{len}");
+        }
+        assert_eq!(
+            Game::decode(&bytes[..bytes.len() - 1]).unwrap().moves.len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_corrupt() {
+        let game = game_from_strings("startpos", &["e2e4", "e7e5"], GameResult::WhiteWin);
+        let bytes = game.encode();
+
+        // Unknown version
+        let mut corrupt = bytes.clone();
+        corrupt[0] = FORMAT_VERSION + 1;
+        assert_eq!(Game::decode(&corrupt), None);
+
+        // Unknown result
+        let mut corrupt = bytes.clone();
+        corrupt[1] = 4;
+        assert_eq!(Game::decode(&corrupt), None);
+
+        // Move indices past the end of the legal moves
+        for idx in [29, 30, 255] {
+            let mut corrupt = bytes.clone();
+            corrupt.push(idx);
+            assert_eq!(Game::decode(&corrupt), None, "{idx}");
+        }
+
+        // A FEN that isn't UTF-8 or isn't a legal position
+        for fen in [&b"\xff\xfe"[..], b"8/8/8/8/8/8/8/8 w - - 0 1"] {
+            let mut corrupt = vec![FORMAT_VERSION, 0];
+            corrupt.extend(u16::try_from(fen.len()).unwrap().to_le_bytes());
+            corrupt.extend(fen);
+            assert_eq!(Game::decode(&corrupt), None);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_illegal() {
+        let game = game_from_strings(
+            "startpos",
+            &["e2e4", "e7e5", "e1e2", "e8e7", "e2e1", "a7a5", "e1g1"],
+            GameResult::Unknown,
+        );
+        let _ = game.encode();
+    }
+}
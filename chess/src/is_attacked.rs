@@ -1,19 +1,10 @@
-use crate::{Piece, Side, position::Position, square::Square};
-
-static KNIGHT: [(i32, i32); 8] = [
-    (-1, 2),
-    (1, 2),
-    (2, 1),
-    (2, -1),
-    (-1, -2),
-    (1, -2),
-    (-2, -1),
-    (-2, 1),
-];
-
-static BISHOP: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
-
-static ROOK: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+use crate::{
+    Piece, Side,
+    mv::{Mv, PromoPiece},
+    position::Position,
+    square::Square,
+    tables::{BISHOP, KNIGHT, ROOK, side_pieces},
+};
 
 impl Position {
     /// Is the square attacked?
@@ -25,6 +16,245 @@ impl Position {
         }
     }
 
+    /// Get the squares of all pieces of the given side that attack the square
+    #[must_use]
+    pub fn attackers_to(&self, sq: Square, side: Side) -> Vec<Square> {
+        let [pawn, knight, bishop, rook, queen, king] = side_pieces(side);
+        let behind = if side == Side::White { -1 } else { 1 };
+        let mut attackers = vec![];
+
+        // Pawns
+        for dx in [-1, 1] {
+            let nx = sq.x as i32 + dx;
+            let ny = sq.y as i32 + behind;
+
+            if !(0..=7).contains(&nx) || !(0..=7).contains(&ny) {
+                continue;
+            }
+
+            let nsq = Square::from_file_rank(nx as u8, ny as u8);
+            if self.get_side_piece_on(nsq) == Some(pawn) {
+                attackers.push(nsq);
+            }
+        }
+
+        // Knights
+        for (dx, dy) in KNIGHT {
+            let nx = sq.x as i32 + dx;
+            let ny = sq.y as i32 + dy;
+
+            if !(0..=7).contains(&nx) || !(0..=7).contains(&ny) {
+                continue;
+            }
+
+            let nsq = Square::from_file_rank(nx as u8, ny as u8);
+            if self.get_side_piece_on(nsq) == Some(knight) {
+                attackers.push(nsq);
+            }
+        }
+
+        // Bishop/Queen
+        for (dx, dy) in BISHOP {
+            if let Some(nsq) = self.first_piece_in_direction(sq, dx, dy) {
+                let found = self.get_side_piece_on(nsq);
+                if found == Some(bishop) || found == Some(queen) {
+                    attackers.push(nsq);
+                }
+            }
+        }
+
+        // Rook/Queen
+        for (dx, dy) in ROOK {
+            if let Some(nsq) = self.first_piece_in_direction(sq, dx, dy) {
+                let found = self.get_side_piece_on(nsq);
+                if found == Some(rook) || found == Some(queen) {
+                    attackers.push(nsq);
+                }
+            }
+        }
+
+        // King
+        if let Some(ksq) = self.ksq[side as usize] {
+            let dx = sq.x as i32 - ksq.x as i32;
+            let dy = sq.y as i32 - ksq.y as i32;
+            if (-1..=1).contains(&dx)
+                && (-1..=1).contains(&dy)
+                && (dx, dy) != (0, 0)
+                && self.get_side_piece_on(ksq) == Some(king)
+            {
+                attackers.push(ksq);
+            }
+        }
+
+        attackers
+    }
+
+    /// Get the square of the least valuable piece of the given side that attacks the square
+    #[must_use]
+    pub fn least_valuable_attacker(&self, sq: Square, side: Side) -> Option<Square> {
+        let [pawn, knight, bishop, rook, queen, king] = side_pieces(side);
+        let behind = if side == Side::White { -1 } else { 1 };
+        let on_board = |dx: i32, dy: i32| {
+            let nx = sq.x as i32 + dx;
+            let ny = sq.y as i32 + dy;
+            ((0..8).contains(&nx) && (0..8).contains(&ny))
+                .then(|| Square::from_file_rank(nx as u8, ny as u8))
+        };
+
+        // Pawns
+        for dx in [-1, 1] {
+            if let Some(nsq) = on_board(dx, behind)
+                && self.get_side_piece_on(nsq) == Some(pawn)
+            {
+                return Some(nsq);
+            }
+        }
+
+        // Knights
+        for (dx, dy) in KNIGHT {
+            if let Some(nsq) = on_board(dx, dy)
+                && self.get_side_piece_on(nsq) == Some(knight)
+            {
+                return Some(nsq);
+            }
+        }
+
+        // Sliders, cheapest first
+        for (dirs, slider) in [
+            (BISHOP, bishop),
+            (ROOK, rook),
+            (BISHOP, queen),
+            (ROOK, queen),
+        ] {
+            for (dx, dy) in dirs {
+                if let Some(nsq) = self.first_piece_in_direction(sq, dx, dy)
+                    && self.get_side_piece_on(nsq) == Some(slider)
+                {
+                    return Some(nsq);
+                }
+            }
+        }
+
+        // King
+        let ksq = self.ksq[side as usize]?;
+        let dx = sq.x as i32 - ksq.x as i32;
+        let dy = sq.y as i32 - ksq.y as i32;
+        ((-1..=1).contains(&dx)
+            && (-1..=1).contains(&dy)
+            && (dx, dy) != (0, 0)
+            && self.get_side_piece_on(ksq) == Some(king))
+        .then_some(ksq)
+    }
+
+    /// Get the squares of the pieces giving check to the side to move
+    #[must_use]
+    pub fn checkers(&self) -> Vec<Square> {
+        self.attackers_to(
+            self.ksq[self.turn as usize].expect("King not found"),
+            !self.turn,
+        )
+    }
+
+    /// Is the side to move in check?
+    #[must_use]
+    pub fn in_check(&self) -> bool {
+        self.is_attacked(
+            self.ksq[self.turn as usize].expect("King not found"),
+            !self.turn,
+        )
+    }
+
+    /// Get the pieces of the given side that are pinned to their king
+    /// Returns pairs of (pinned piece square, pinning piece square)
+    #[must_use]
+    pub fn pinned_pieces(&self, side: Side) -> Vec<(Square, Square)> {
+        let ksq = self.ksq[side as usize].expect("King not found");
+        let [_, _, bishop, rook, queen, _] = side_pieces(!side);
+        let mut pinned = vec![];
+
+        for (dirs, slider) in [(BISHOP, bishop), (ROOK, rook)] {
+            for (dx, dy) in dirs {
+                let Some(first) = self.first_piece_in_direction(ksq, dx, dy) else {
+                    continue;
+                };
+                if self.get_side_on(first) != Some(side) {
+                    continue;
+                }
+
+                let Some(second) = self.first_piece_in_direction(first, dx, dy) else {
+                    continue;
+                };
+                let found = self.get_side_piece_on(second);
+                if found == Some(slider) || found == Some(queen) {
+                    pinned.push((first, second));
+                }
+            }
+        }
+
+        pinned
+    }
+
+    /// Does the pseudolegal move give check to the opponent?
+    /// Only the pieces are moved on a copy of the board, the rest of the state is untouched
+    #[must_use]
+    pub fn gives_check(&self, mv: &Mv) -> bool {
+        let piece = self
+            .get_side_piece_on(mv.from)
+            .expect("No piece on move origin square");
+        let [pawn, knight, bishop, rook, queen, king] = side_pieces(self.turn);
+        let mut board = *self;
+
+        board.clear_square(mv.from);
+        board.set_piece(
+            match mv.promo {
+                Some(PromoPiece::Knight) => knight,
+                Some(PromoPiece::Bishop) => bishop,
+                Some(PromoPiece::Rook) => rook,
+                Some(PromoPiece::Queen) => queen,
+                None => piece,
+            },
+            mv.to,
+        );
+
+        // En passant captures remove the pawn behind the destination
+        if piece == pawn && self.ep == Some(mv.to) {
+            board.clear_square(Square::from_file_rank(mv.to.x, mv.from.y));
+        }
+
+        // Castling also moves the rook
+        if piece == king && (mv.from.x as i32 - mv.to.x as i32).abs() == 2 {
+            let (rook_from, rook_to) = if mv.to.x == 6 { (7, 5) } else { (0, 3) };
+            board.clear_square(Square::from_file_rank(rook_from, mv.from.y));
+            board.set_piece(rook, Square::from_file_rank(rook_to, mv.from.y));
+        }
+
+        if piece == king {
+            board.ksq[self.turn as usize] = Some(mv.to);
+        }
+
+        board.is_attacked(
+            self.ksq[!self.turn as usize].expect("King not found"),
+            self.turn,
+        )
+    }
+
+    /// Find the first occupied square from the given square in a direction
+    fn first_piece_in_direction(&self, sq: Square, dx: i32, dy: i32) -> Option<Square> {
+        let mut nx = sq.x as i32 + dx;
+        let mut ny = sq.y as i32 + dy;
+
+        while (0..8).contains(&nx) && (0..8).contains(&ny) {
+            let nsq = Square::from_file_rank(nx as u8, ny as u8);
+            if self.get_side_piece_on(nsq).is_some() {
+                return Some(nsq);
+            }
+            nx += dx;
+            ny += dy;
+        }
+
+        None
+    }
+
     /// Is the square attacked by white?
     #[must_use]
     fn is_attacked_white(&self, sq: Square) -> bool {
@@ -193,6 +423,7 @@ impl Position {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::perft::tests::TESTS;
 
     #[test]
     fn test_is_attacked_black() {
@@ -212,4 +443,141 @@ mod tests {
             assert!(!pos.is_attacked_black(sq), "{}", sq);
         }
     }
+
+    fn squares(sqstrs: &[&str]) -> Vec<Square> {
+        let mut sqs = sqstrs
+            .iter()
+            .map(|sqstr| Square::from_string(sqstr))
+            .collect::<Vec<_>>();
+        sqs.sort_by_key(|sq| (sq.y, sq.x));
+        sqs
+    }
+
+    fn sorted(mut sqs: Vec<Square>) -> Vec<Square> {
+        sqs.sort_by_key(|sq| (sq.y, sq.x));
+        sqs
+    }
+
+    #[test]
+    fn test_attackers_to() {
+        let pos = Position::from_fen("4k3/1P2r3/1q6/5N2/2n3b1/4Q1p1/3n4/R3K2R w KQ - 0 1");
+        let tests = [
+            ("e2", Side::Black, vec!["g4"]),
+            ("f2", Side::Black, vec!["g3"]),
+            ("d1", Side::Black, vec!["g4"]),
+            ("e5", Side::White, vec!["e3"]),
+            ("d2", Side::White, vec!["e1", "e3"]),
+            ("a8", Side::White, vec!["a1", "b7"]),
+            ("c6", Side::White, vec![]),
+            ("g7", Side::White, vec!["f5"]),
+        ];
+
+        for (sqstr, side, expected) in tests {
+            let sq = Square::from_string(sqstr);
+            assert_eq!(
+                sorted(pos.attackers_to(sq, side)),
+                squares(&expected),
+                "{sqstr}"
+            );
+            assert_eq!(!expected.is_empty(), pos.is_attacked(sq, side), "{sqstr}");
+            assert_eq!(
+                pos.least_valuable_attacker(sq, side),
+                pos.attackers_to(sq, side).into_iter().min_by_key(|sq| pos
+                    .get_side_piece_on(*sq)
+                    .unwrap()
+                    as usize
+                    % 6),
+                "{sqstr}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_checkers() {
+        let tests = [
+            ("startpos", vec![]),
+            ("4k3/8/4r3/8/8/8/3p4/4K3 w - - 0 1", vec!["d2", "e6"]),
+            ("4k3/8/4q3/8/8/8/3b4/4K3 w - - 0 1", vec!["d2", "e6"]),
+            ("4k3/8/8/8/8/5n2/8/4K3 w - - 0 1", vec!["f3"]),
+            ("4k3/8/8/8/8/8/8/4K2R b - - 0 1", vec![]),
+            ("4k2R/8/8/8/8/8/8/4K3 b - - 0 1", vec!["h8"]),
+        ];
+
+        for (fen, expected) in tests {
+            let pos = Position::from_fen(fen);
+            assert_eq!(sorted(pos.checkers()), squares(&expected), "{fen}");
+            assert_eq!(pos.in_check(), !expected.is_empty(), "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_pinned_pieces() {
+        let pos = Position::from_fen("4k3/8/8/8/4r3/8/3QP3/4K3 w - - 0 1");
+        assert_eq!(
+            pos.pinned_pieces(Side::White),
+            vec![(Square::from_string("e2"), Square::from_string("e4"))]
+        );
+        assert!(pos.pinned_pieces(Side::Black).is_empty());
+
+        let pos = Position::from_fen("4k3/8/8/8/1b5b/8/3Q1P2/4K3 w - - 0 1");
+        let mut pinned = pos.pinned_pieces(Side::White);
+        pinned.sort_by_key(|(sq, _)| sq.x);
+        assert_eq!(
+            pinned,
+            vec![
+                (Square::from_string("d2"), Square::from_string("b4")),
+                (Square::from_string("f2"), Square::from_string("h4")),
+            ]
+        );
+
+        // Two pieces between the king and the slider are not pinned
+        let pos = Position::from_fen("4k3/4r3/8/8/8/4P3/4N3/4K3 w - - 0 1");
+        assert!(pos.pinned_pieces(Side::White).is_empty());
+    }
+
+    #[test]
+    fn test_gives_check() {
+        let tests = [
+            // Direct checks
+            ("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8", true),
+            ("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a7", false),
+            ("4k3/8/8/8/8/8/8/1N2K3 w - - 0 1", "b1d2", false),
+            ("4k3/8/8/8/8/8/3N4/4K3 w - - 0 1", "d2f3", false),
+            ("4k3/8/8/8/8/5N2/8/4K3 w - - 0 1", "f3d6", true),
+            ("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1", "e8d8", false),
+            ("4k3/8/8/8/8/8/3p4/4K3 b - - 0 1", "d2c1q", true),
+            ("4k3/8/8/8/8/8/3p4/4K3 b - - 0 1", "d2c1n", false),
+            // Discovered checks
+            ("4k3/8/8/8/4N3/8/8/4RK2 w - - 0 1", "e4c5", true),
+            ("4k3/8/8/8/4N3/8/8/4RK2 w - - 0 1", "e4e5", false),
+            // En passant discovered check
+            ("8/8/8/k1pP3Q/8/8/8/4K3 w - c6 0 1", "d5c6", true),
+            // Castling
+            ("5k2/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1", true),
+            ("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", "e1c1", true),
+            ("4k3/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1", false),
+        ];
+
+        for (fen, movestr, expected) in tests {
+            let pos = Position::from_fen(fen);
+            let mv = Mv::from_string(movestr);
+            assert_eq!(pos.gives_check(&mv), expected, "{fen} {movestr}");
+
+            let mut npos = pos;
+            let _ = npos.makemove(&mv);
+            assert_eq!(npos.in_check(), expected, "{fen} {movestr}");
+        }
+    }
+
+    #[test]
+    fn test_gives_check_perft() {
+        for (fen, _) in TESTS {
+            let pos = Position::from_fen(fen);
+            for mv in pos.legal_moves() {
+                let mut npos = pos;
+                let _ = npos.makemove(&mv);
+                assert_eq!(pos.gives_check(&mv), npos.in_check(), "{fen} {mv}");
+            }
+        }
+    }
 }
@@ -1,15 +1,28 @@
 use std::ops::Not;
 
+pub mod encode;
 pub mod fen;
+pub mod find_move;
+pub mod game;
 pub mod is_attacked;
 pub mod makemove;
 pub mod movegen;
 pub mod mv;
 pub mod perft;
 pub mod position;
+pub mod random;
+pub mod see;
+pub mod selfcheck;
+#[cfg(feature = "serde")]
+mod serde_impls;
 pub mod square;
+pub mod svg;
+mod tables;
+pub mod transform;
+pub mod unmove;
+pub mod zobrist;
 
-#[derive(PartialEq, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
 pub enum Piece {
     WP,
     WN,
@@ -25,12 +38,13 @@ pub enum Piece {
     BK,
 }
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
 pub enum Side {
     White,
     Black,
 }
 
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
 pub enum Castling {
     WKS,
     WQS,
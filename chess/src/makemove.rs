@@ -3,9 +3,52 @@ use crate::{
     mv::{Mv, PromoPiece},
     position::Position,
     square::Square,
+    zobrist::{castling_key, side_key},
 };
 
+/// The state a null move loses, needed to undo it
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct NullMoveUndo {
+    ep: Option<Square>,
+    halfmoves: u16,
+    fullmoves: u16,
+}
+
 impl Position {
+    /// Pass the turn to the opponent without moving a piece
+    /// Returns `None` without changing the position if the side to move is in check
+    #[must_use]
+    pub fn make_null_move(&mut self) -> Option<NullMoveUndo> {
+        if self.in_check() {
+            return None;
+        }
+
+        let undo = NullMoveUndo {
+            ep: self.ep,
+            halfmoves: self.halfmoves,
+            fullmoves: self.fullmoves,
+        };
+
+        self.ep = None;
+        self.halfmoves = self.halfmoves.saturating_add(1);
+        if self.turn == Side::Black {
+            self.fullmoves = self.fullmoves.saturating_add(1);
+        }
+        self.turn = !self.turn;
+        self.key ^= side_key(Side::Black);
+
+        Some(undo)
+    }
+
+    /// Undo a null move made by `make_null_move`
+    pub fn unmake_null_move(&mut self, undo: NullMoveUndo) {
+        self.turn = !self.turn;
+        self.key ^= side_key(Side::Black);
+        self.ep = undo.ep;
+        self.halfmoves = undo.halfmoves;
+        self.fullmoves = undo.fullmoves;
+    }
+
     /// Apply a pseudolegal move to the board
     /// Returns whether the move was legal or not
     #[must_use]
@@ -15,8 +58,9 @@ impl Position {
             .expect("No piece on move origin square");
 
         let captured = self.get_side_piece_on(mv.to);
+        let castling = self.castling;
 
-        self.halfmoves += 1;
+        self.halfmoves = self.halfmoves.saturating_add(1);
 
         // Remove piece
         self.clear_square(mv.from);
@@ -146,9 +190,18 @@ impl Position {
             self.ksq[Side::Black as usize] = Some(mv.to);
         }
 
+        // Fullmoves are incremented after black moves
+        if self.turn == Side::Black {
+            self.fullmoves = self.fullmoves.saturating_add(1);
+        }
+
         // Side to move
         self.turn = !self.turn;
 
+        // The pieces are already hashed as they moved
+        self.key ^= side_key(Side::Black) ^ castling_key(&castling) ^ castling_key(&self.castling);
+        debug_assert_eq!(self.key, self.board_key(), "Stale Zobrist key");
+
         // Legality check
         !self.is_attacked(self.ksq[!self.turn as usize].unwrap(), self.turn)
     }
@@ -220,4 +273,36 @@ mod tests {
             assert_eq!(pos.get_fen(), fen);
         }
     }
+
+    #[test]
+    fn test_null_move() {
+        let tests = [
+            (
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2",
+            ),
+            (
+                "r3k2r/6P1/8/3pP3/8/8/4P3/R3K2R w KQkq d6 5 10",
+                "r3k2r/6P1/8/3pP3/8/8/4P3/R3K2R b KQkq - 6 10",
+            ),
+        ];
+
+        for (fen, expected) in tests {
+            let mut pos = Position::from_fen(fen);
+            let undo = pos.make_null_move().expect("Null move refused");
+            assert_eq!(pos.get_fen(), expected);
+
+            pos.unmake_null_move(undo);
+            assert_eq!(pos.get_fen(), fen);
+            assert_eq!(pos, Position::from_fen(fen));
+        }
+    }
+
+    #[test]
+    fn test_null_move_in_check() {
+        let fen = "4k3/8/4r3/8/8/8/8/4K3 w - - 0 1";
+        let mut pos = Position::from_fen(fen);
+        assert!(pos.make_null_move().is_none());
+        assert_eq!(pos.get_fen(), fen);
+    }
 }
@@ -3,46 +3,36 @@ use crate::{
     mv::{Mv, PromoPiece},
     position::Position,
     square::Square,
+    tables::{BISHOP, KING, KNIGHT, QUEEN, ROOK},
 };
 
-static KNIGHT: [(i32, i32); 8] = [
-    (-1, 2),
-    (1, 2),
-    (2, 1),
-    (2, -1),
-    (-1, -2),
-    (1, -2),
-    (-2, -1),
-    (-2, 1),
-];
-
-static BISHOP: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
-
-static ROOK: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
-
-static QUEEN: [(i32, i32); 8] = [
-    (1, 1),
-    (1, -1),
-    (-1, -1),
-    (-1, 1),
-    (1, 0),
-    (-1, 0),
-    (0, 1),
-    (0, -1),
-];
-
-static KING: [(i32, i32); 8] = [
-    (-1, -1),
-    (-1, 0),
-    (-1, 1),
-    (0, -1),
-    (0, 1),
-    (1, -1),
-    (1, 0),
-    (1, 1),
-];
-
-fn generate_nonsliding(pos: &Position, movelist: &mut Vec<Mv>, from: Square, dirs: &[(i32, i32)]) {
+/// Which kinds of moves to generate
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+enum GenType {
+    All,
+    /// Captures and promotions
+    Noisy,
+    /// Everything else
+    Quiet,
+}
+
+impl GenType {
+    fn noisy(self) -> bool {
+        self != GenType::Quiet
+    }
+
+    fn quiet(self) -> bool {
+        self != GenType::Noisy
+    }
+}
+
+fn generate_nonsliding(
+    pos: &Position,
+    movelist: &mut Vec<Mv>,
+    from: Square,
+    dirs: &[(i32, i32)],
+    gen_type: GenType,
+) {
     for (dx, dy) in dirs {
         let nx = from.x as i32 + dx;
         let ny = from.y as i32 + dy;
@@ -59,6 +49,12 @@ fn generate_nonsliding(pos: &Position, movelist: &mut Vec<Mv>, from: Square, dir
             continue;
         }
 
+        // Not the kind of move we want
+        let is_capture = pos.get_side_on(to).is_some();
+        if (is_capture && !gen_type.noisy()) || (!is_capture && !gen_type.quiet()) {
+            continue;
+        }
+
         movelist.push(Mv {
             from,
             to,
@@ -67,7 +63,13 @@ fn generate_nonsliding(pos: &Position, movelist: &mut Vec<Mv>, from: Square, dir
     }
 }
 
-fn generate_sliding(pos: &Position, movelist: &mut Vec<Mv>, from: Square, dirs: &[(i32, i32)]) {
+fn generate_sliding(
+    pos: &Position,
+    movelist: &mut Vec<Mv>,
+    from: Square,
+    dirs: &[(i32, i32)],
+    gen_type: GenType,
+) {
     for (dx, dy) in dirs {
         let mut nx = from.x as i32 + dx;
         let mut ny = from.y as i32 + dy;
@@ -77,12 +79,15 @@ fn generate_sliding(pos: &Position, movelist: &mut Vec<Mv>, from: Square, dirs:
             if pos.get_side_on(to) == Some(pos.turn) {
                 break;
             }
-            movelist.push(Mv {
-                from,
-                to,
-                promo: None,
-            });
-            if pos.get_side_on(to) == Some(!pos.turn) {
+            let is_capture = pos.get_side_on(to) == Some(!pos.turn);
+            if (is_capture && gen_type.noisy()) || (!is_capture && gen_type.quiet()) {
+                movelist.push(Mv {
+                    from,
+                    to,
+                    promo: None,
+                });
+            }
+            if is_capture {
                 break;
             }
             nx += dx;
@@ -115,10 +120,101 @@ fn push_promotions(movelist: &mut Vec<Mv>, from: Square, to: Square) {
 }
 
 impl Position {
+    /// Generate legal moves
+    /// The order matches `pseudolegal_moves` with illegal moves removed
+    #[must_use]
+    pub fn legal_moves(&self) -> Vec<Mv> {
+        self.pseudolegal_moves()
+            .into_iter()
+            .filter(|mv| {
+                let mut npos = *self;
+                npos.makemove(mv)
+            })
+            .collect()
+    }
+
     /// Generate pseudolegal moves
     /// This includes moves that leave the king in check
     #[must_use]
     pub fn pseudolegal_moves(&self) -> Vec<Mv> {
+        self.generate(GenType::All)
+    }
+
+    /// Generate pseudolegal captures, including en passant, and promotions
+    #[must_use]
+    pub fn captures_and_promotions(&self) -> Vec<Mv> {
+        self.generate(GenType::Noisy)
+    }
+
+    /// Generate pseudolegal moves that are neither captures nor promotions
+    /// This includes castling
+    #[must_use]
+    pub fn quiet_moves(&self) -> Vec<Mv> {
+        self.generate(GenType::Quiet)
+    }
+
+    /// Generate pseudolegal quiet moves that give check
+    #[must_use]
+    pub fn quiet_checks(&self) -> Vec<Mv> {
+        self.quiet_moves()
+            .into_iter()
+            .filter(|mv| self.gives_check(mv))
+            .collect()
+    }
+
+    /// Generate pseudolegal moves that might get the side to move out of check
+    /// These are king moves, captures of a single checker, and blocks of a single sliding checker
+    /// If the side to move isn't in check, this is every pseudolegal move
+    #[must_use]
+    pub fn check_evasions(&self) -> Vec<Mv> {
+        let ksq = self.ksq[self.turn as usize].expect("ksq not found");
+        let checkers = self.checkers();
+
+        if checkers.is_empty() {
+            return self.pseudolegal_moves();
+        }
+
+        // Squares a non-king move can go to, only available against a single checker
+        let mut targets = vec![];
+        if let [checker] = checkers[..] {
+            targets.push(checker);
+
+            // Squares between a sliding checker and the king
+            let dx = (checker.x as i32 - ksq.x as i32).signum();
+            let dy = (checker.y as i32 - ksq.y as i32).signum();
+            let is_line = checker.x == ksq.x
+                || checker.y == ksq.y
+                || (checker.x as i32 - ksq.x as i32).abs()
+                    == (checker.y as i32 - ksq.y as i32).abs();
+            if is_line {
+                let mut nx = ksq.x as i32 + dx;
+                let mut ny = ksq.y as i32 + dy;
+                while (nx, ny) != (checker.x as i32, checker.y as i32) {
+                    targets.push(Square::from_file_rank(nx as u8, ny as u8));
+                    nx += dx;
+                    ny += dy;
+                }
+            }
+        }
+
+        self.pseudolegal_moves()
+            .into_iter()
+            .filter(|mv| {
+                let piece = self.get_side_piece_on(mv.from);
+                let is_king = piece == Some(Piece::WK) || piece == Some(Piece::BK);
+                let is_pawn = piece == Some(Piece::WP) || piece == Some(Piece::BP);
+
+                // En passant captures the checking pawn from behind the destination square
+                let is_ep_capture = is_pawn
+                    && self.ep == Some(mv.to)
+                    && targets.contains(&Square::from_file_rank(mv.to.x, mv.from.y));
+
+                is_king || targets.contains(&mv.to) || is_ep_capture
+            })
+            .collect()
+    }
+
+    fn generate(&self, gen_type: GenType) -> Vec<Mv> {
         let mut movelist = vec![];
 
         for x in 0..8i32 {
@@ -152,8 +248,8 @@ impl Position {
                             let is_ep = self.ep == Some(to);
                             let is_capture = is_ep || self.get_side_on(to) == Some(!self.turn);
 
-                            // Not a capture or EP
-                            if !is_capture && !is_ep {
+                            // Not a capture or EP, or captures aren't wanted
+                            if (!is_capture && !is_ep) || !gen_type.noisy() {
                                 continue;
                             }
 
@@ -169,7 +265,8 @@ impl Position {
                         }
 
                         // Double move
-                        if y == double_rank
+                        if gen_type.quiet()
+                            && y == double_rank
                             && let double =
                                 Square::from_file_rank(x as u8, (y + forwards + forwards) as u8)
                             && self.get_side_on(single).is_none()
@@ -184,9 +281,9 @@ impl Position {
 
                         // Single move
                         if self.get_side_on(single).is_none() {
-                            if is_promo {
+                            if is_promo && gen_type.noisy() {
                                 push_promotions(&mut movelist, from, single);
-                            } else {
+                            } else if !is_promo && gen_type.quiet() {
                                 movelist.push(Mv {
                                     from,
                                     to: single,
@@ -196,19 +293,19 @@ impl Position {
                         }
                     }
                     Some(Piece::WN) | Some(Piece::BN) => {
-                        generate_nonsliding(self, &mut movelist, from, &KNIGHT)
+                        generate_nonsliding(self, &mut movelist, from, &KNIGHT, gen_type)
                     }
                     Some(Piece::WB) | Some(Piece::BB) => {
-                        generate_sliding(self, &mut movelist, from, &BISHOP)
+                        generate_sliding(self, &mut movelist, from, &BISHOP, gen_type)
                     }
                     Some(Piece::WR) | Some(Piece::BR) => {
-                        generate_sliding(self, &mut movelist, from, &ROOK)
+                        generate_sliding(self, &mut movelist, from, &ROOK, gen_type)
                     }
                     Some(Piece::WQ) | Some(Piece::BQ) => {
-                        generate_sliding(self, &mut movelist, from, &QUEEN)
+                        generate_sliding(self, &mut movelist, from, &QUEEN, gen_type)
                     }
                     Some(Piece::WK) | Some(Piece::BK) => {
-                        generate_nonsliding(self, &mut movelist, from, &KING)
+                        generate_nonsliding(self, &mut movelist, from, &KING, gen_type)
                     }
                     None => unreachable!("Empty square already accounted for"),
                 }
@@ -224,6 +321,7 @@ impl Position {
         if self.turn == Side::White
             && self.castling[Castling::WKS as usize]
             && !in_check
+            && gen_type.quiet()
             && self.get_side_piece_on(Square::from_index(5)).is_none()
             && self.get_side_piece_on(Square::from_index(6)).is_none()
             && !self.is_attacked(Square::from_index(5), !self.turn)
@@ -240,6 +338,7 @@ impl Position {
         if self.turn == Side::White
             && self.castling[Castling::WQS as usize]
             && !in_check
+            && gen_type.quiet()
             && self.get_side_piece_on(Square::from_index(3)).is_none()
             && self.get_side_piece_on(Square::from_index(2)).is_none()
             && self.get_side_piece_on(Square::from_index(1)).is_none()
@@ -257,6 +356,7 @@ impl Position {
         if self.turn == Side::Black
             && self.castling[Castling::BKS as usize]
             && !in_check
+            && gen_type.quiet()
             && self.get_side_piece_on(Square::from_index(61)).is_none()
             && self.get_side_piece_on(Square::from_index(62)).is_none()
             && !self.is_attacked(Square::from_index(61), !self.turn)
@@ -273,6 +373,7 @@ impl Position {
         if self.turn == Side::Black
             && self.castling[Castling::BQS as usize]
             && !in_check
+            && gen_type.quiet()
             && self.get_side_piece_on(Square::from_index(59)).is_none()
             && self.get_side_piece_on(Square::from_index(58)).is_none()
             && self.get_side_piece_on(Square::from_index(57)).is_none()
@@ -289,3 +390,107 @@ impl Position {
         movelist
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{mv::Mv, perft::tests::TESTS, position::Position};
+    use std::collections::HashSet;
+
+    /// Every perft position, plus every position one legal move later
+    fn positions() -> Vec<Position> {
+        let mut positions = vec![];
+        for (fen, _) in TESTS {
+            let pos = Position::from_fen(fen);
+            positions.push(pos);
+            for mv in pos.legal_moves() {
+                let mut npos = pos;
+                let _ = npos.makemove(&mv);
+                positions.push(npos);
+            }
+        }
+        positions
+    }
+
+    fn legal(pos: &Position, moves: Vec<Mv>) -> HashSet<Mv> {
+        moves
+            .into_iter()
+            .filter(|mv| {
+                let mut npos = *pos;
+                npos.makemove(mv)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        for (fen, results) in TESTS {
+            let pos = Position::from_fen(fen);
+            assert_eq!(pos.legal_moves().len() as u64, results[0], "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_staged_union() {
+        for pos in positions() {
+            let all = pos.pseudolegal_moves();
+            let noisy = pos.captures_and_promotions();
+            let quiet = pos.quiet_moves();
+            let fen = pos.get_fen();
+
+            assert_eq!(all.len(), noisy.len() + quiet.len(), "{fen}");
+
+            let all = all.into_iter().collect::<HashSet<_>>();
+            let staged = noisy
+                .iter()
+                .chain(quiet.iter())
+                .copied()
+                .collect::<HashSet<_>>();
+            assert_eq!(all, staged, "{fen}");
+
+            for mv in noisy {
+                assert!(
+                    mv.promo.is_some() || pos.get_side_on(mv.to).is_some() || pos.ep == Some(mv.to),
+                    "{fen} {mv}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_evasions() {
+        let mut checks = 0;
+        for pos in positions() {
+            let fen = pos.get_fen();
+            let evasions = pos.check_evasions();
+
+            if pos.in_check() {
+                checks += 1;
+                assert!(evasions.len() <= pos.pseudolegal_moves().len(), "{fen}");
+            }
+            assert_eq!(
+                legal(&pos, evasions),
+                legal(&pos, pos.pseudolegal_moves()),
+                "{fen}"
+            );
+        }
+        assert!(checks > 100);
+    }
+
+    #[test]
+    fn test_quiet_checks() {
+        for pos in positions() {
+            let fen = pos.get_fen();
+            let checks = pos.quiet_checks().into_iter().collect::<HashSet<_>>();
+            let expected = pos
+                .quiet_moves()
+                .into_iter()
+                .filter(|mv| {
+                    let mut npos = pos;
+                    let _ = npos.makemove(mv);
+                    npos.in_check()
+                })
+                .collect::<HashSet<_>>();
+            assert_eq!(checks, expected, "{fen}");
+        }
+    }
+}
@@ -2,7 +2,7 @@ use crate::square::Square;
 use std::fmt::Display;
 
 /// Promotion types
-#[derive(Copy, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
 pub enum PromoPiece {
     Knight,
     Bishop,
@@ -11,6 +11,7 @@ pub enum PromoPiece {
 }
 
 /// The move struct
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
 pub struct Mv {
     pub from: Square,
     pub to: Square,
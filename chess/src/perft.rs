@@ -23,10 +23,10 @@ pub fn perft(pos: &Position, depth: i32) -> u64 {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
-    static TESTS: [(&str, [u64; 3]); 171] = [
+    pub(crate) static TESTS: [(&str, [u64; 3]); 171] = [
         (
             "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
             [20, 400, 8902],
@@ -1,15 +1,21 @@
-use crate::{Piece, Side, square::Square};
+use crate::{Castling, Piece, Side, square::Square, zobrist::piece_key};
+use std::hash::{Hash, Hasher};
 
 /// This struct holds all the information about a chess position.
-#[derive(Clone, Copy)]
+///
+/// The Zobrist key is cached, and `set_piece` and `clear_square` keep it up to date.
+/// After writing to `board`, `turn` or `castling` directly, call `refresh_key`.
+#[derive(Debug, Clone, Copy)]
 pub struct Position {
     pub board: [[Option<Piece>; 8]; 8],
     pub turn: Side,
-    pub halfmoves: u8,
-    pub fullmoves: u8,
+    pub halfmoves: u16,
+    pub fullmoves: u16,
     pub ep: Option<Square>,
     pub castling: [bool; 4],
     pub ksq: [Option<Square>; 2],
+    /// The Zobrist key without the en passant square, kept up to date as the position changes
+    pub(crate) key: u64,
 }
 
 impl Default for Position {
@@ -22,11 +28,119 @@ impl Default for Position {
             ep: None,
             castling: [false; 4],
             ksq: [None; 2],
+            key: 0,
         }
     }
 }
 
+/// Positions are equal if their game state is equal
+/// The king square cache and Zobrist key are derived from the rest, so they are not compared
+impl PartialEq for Position {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board
+            && self.turn == other.turn
+            && self.castling == other.castling
+            && self.ep == other.ep
+            && self.halfmoves == other.halfmoves
+            && self.fullmoves == other.fullmoves
+    }
+}
+
+impl Eq for Position {}
+
+impl Hash for Position {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.board.hash(state);
+        self.turn.hash(state);
+        self.castling.hash(state);
+        self.ep.hash(state);
+        self.halfmoves.hash(state);
+        self.fullmoves.hash(state);
+    }
+}
+
 impl Position {
+    /// Are the positions the same for the purpose of repetition?
+    /// This ignores the move counters, and only considers the en passant
+    /// square if an en passant capture is legal
+    #[must_use]
+    pub fn eq_ignoring_counters(&self, other: &Self) -> bool {
+        let ep = if self.has_legal_ep() { self.ep } else { None };
+        let other_ep = if other.has_legal_ep() { other.ep } else { None };
+
+        self.board == other.board
+            && self.turn == other.turn
+            && self.castling == other.castling
+            && ep == other_ep
+    }
+
+    /// Is the position one the move generator and search can safely use?
+    /// Each side needs exactly one king and at most 16 pieces,
+    /// pawns can't be on the first or last rank, castling permissions need the king
+    /// and rook on their starting squares, the en passant square has to be behind a pawn that just moved two squares,
+    /// and the side that just moved can't be left in check
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        let mut kings = [0; 2];
+        let mut pieces = [0; 2];
+        for x in 0..8 {
+            for y in 0..8 {
+                if let Some(side) = self.get_side_on(Square::from_file_rank(x as u8, y as u8)) {
+                    pieces[side as usize] += 1;
+                }
+                match self.board[x][y] {
+                    Some(Piece::WK) => kings[Side::White as usize] += 1,
+                    Some(Piece::BK) => kings[Side::Black as usize] += 1,
+                    Some(Piece::WP | Piece::BP) if y == 0 || y == 7 => return false,
+                    _ => {}
+                }
+            }
+        }
+        if kings != [1, 1] || pieces.iter().any(|&count| count > 16) {
+            return false;
+        }
+
+        let (Some(ksq), Some(opponent_ksq)) =
+            (self.ksq[self.turn as usize], self.ksq[!self.turn as usize])
+        else {
+            return false;
+        };
+        if self.get_side_piece_on(ksq).is_none() || self.get_side_piece_on(opponent_ksq).is_none() {
+            return false;
+        }
+
+        let castling = [
+            (Castling::WKS, Piece::WK, 4, Piece::WR, 7),
+            (Castling::WQS, Piece::WK, 4, Piece::WR, 0),
+            (Castling::BKS, Piece::BK, 60, Piece::BR, 63),
+            (Castling::BQS, Piece::BK, 60, Piece::BR, 56),
+        ];
+        for (perm, king, ksq, rook, rsq) in castling {
+            if self.castling[perm as usize]
+                && (self.get_side_piece_on(Square::from_index(ksq)) != Some(king)
+                    || self.get_side_piece_on(Square::from_index(rsq)) != Some(rook))
+            {
+                return false;
+            }
+        }
+
+        if let Some(ep) = self.ep {
+            let (ep_rank, pawn_rank, pawn) = match self.turn {
+                Side::White => (5, 4, Piece::BP),
+                Side::Black => (2, 3, Piece::WP),
+            };
+            if ep.x >= 8
+                || ep.y != ep_rank
+                || self.get_side_piece_on(ep).is_some()
+                || self.get_side_piece_on(Square::from_file_rank(ep.x, pawn_rank)) != Some(pawn)
+            {
+                return false;
+            }
+        }
+
+        !self.is_attacked(opponent_ksq, self.turn)
+    }
+
     /// Return what piece, if any, is on the given square
     #[must_use]
     pub fn get_side_piece_on(&self, sq: Square) -> Option<Piece> {
@@ -35,11 +149,16 @@ impl Position {
 
     /// Place a piece on the board
     pub fn set_piece(&mut self, piece: Piece, sq: Square) {
+        self.clear_square(sq);
+        self.key ^= piece_key(piece, sq);
         self.board[sq.x as usize][sq.y as usize] = Some(piece);
     }
 
     /// Clear a square on the board
     pub fn clear_square(&mut self, sq: Square) {
+        if let Some(piece) = self.get_side_piece_on(sq) {
+            self.key ^= piece_key(piece, sq);
+        }
         self.board[sq.x as usize][sq.y as usize] = None;
     }
 
@@ -64,3 +183,52 @@ impl Position {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mv::Mv;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_eq() {
+        let a = Position::from_fen("startpos");
+        let b = Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let c = Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 4 3");
+        let d = Position::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1");
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(a, d);
+        assert!(a.eq_ignoring_counters(&c));
+        assert!(!a.eq_ignoring_counters(&d));
+    }
+
+    #[test]
+    fn test_eq_ep() {
+        // EP square with no capture available
+        let a = Position::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1");
+        let b = Position::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1");
+        assert_ne!(a, b);
+        assert!(a.eq_ignoring_counters(&b));
+
+        // EP square with a capture available
+        let a = Position::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1");
+        let b = Position::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1");
+        assert!(!a.eq_ignoring_counters(&b));
+    }
+
+    #[test]
+    fn test_repetition() {
+        let mut pos = Position::from_fen("startpos");
+        let mut seen = HashSet::new();
+        seen.insert(pos);
+
+        for movestr in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+            assert!(pos.makemove(&Mv::from_string(movestr)));
+        }
+
+        assert!(!seen.contains(&pos));
+        assert!(pos.eq_ignoring_counters(&Position::from_fen("startpos")));
+    }
+}
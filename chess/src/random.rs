@@ -0,0 +1,265 @@
+use crate::{
+    Piece, Side,
+    game::{Game, GameResult},
+    position::Position,
+    square::Square,
+};
+
+/// The most placements tried before giving up on a material signature
+const MAX_ATTEMPTS: usize = 1000;
+
+/// A small seedable random number generator (SplitMix64)
+/// This keeps results reproducible without any external dependencies
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Create a generator from a seed
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Get the next random number
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Get a random number in the range `0..n`
+    /// Uses a widening multiply, rejecting the few values that would bias the result
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "Empty range");
+        let n = n as u64;
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let product = u128::from(self.next_u64()) * u128::from(n);
+            if product as u64 >= threshold {
+                return (product >> 64) as usize;
+            }
+        }
+    }
+}
+
+/// Play random legal moves from the position
+/// The game stops early if it reaches checkmate or stalemate
+#[must_use]
+pub fn random_game(start: &Position, plies: usize, rng: &mut Rng) -> Game {
+    let mut game = Game::new(*start);
+    let mut pos = *start;
+
+    for _ in 0..plies {
+        let moves = pos.legal_moves();
+        if moves.is_empty() {
+            break;
+        }
+
+        let mv = moves[rng.below(moves.len())];
+        let success = pos.makemove(&mv);
+        debug_assert!(success);
+        game.moves.push(mv);
+    }
+
+    if pos.legal_moves().is_empty() {
+        game.result = match (pos.in_check(), pos.turn) {
+            (true, Side::White) => GameResult::BlackWin,
+            (true, Side::Black) => GameResult::WhiteWin,
+            (false, _) => GameResult::Draw,
+        };
+    }
+
+    game
+}
+
+/// Parse one side of a material signature such as "KRP"
+fn parse_side(word: &str, side: Side) -> Vec<Piece> {
+    let pieces = word
+        .chars()
+        .map(|c| match (c.to_ascii_uppercase(), side) {
+            ('P', Side::White) => Piece::WP,
+            ('N', Side::White) => Piece::WN,
+            ('B', Side::White) => Piece::WB,
+            ('R', Side::White) => Piece::WR,
+            ('Q', Side::White) => Piece::WQ,
+            ('K', Side::White) => Piece::WK,
+            ('P', Side::Black) => Piece::BP,
+            ('N', Side::Black) => Piece::BN,
+            ('B', Side::Black) => Piece::BB,
+            ('R', Side::Black) => Piece::BR,
+            ('Q', Side::Black) => Piece::BQ,
+            ('K', Side::Black) => Piece::BK,
+            _ => panic!("Unrecognised piece in material signature"),
+        })
+        .collect::<Vec<_>>();
+
+    let kings = pieces
+        .iter()
+        .filter(|piece| **piece == Piece::WK || **piece == Piece::BK)
+        .count();
+    assert_eq!(kings, 1, "Each side needs exactly one king");
+
+    pieces
+}
+
+/// Generate a random legal position with the material given, such as "KRPvKR"
+/// The side to move is random, and the side not to move is never in check
+/// Returns None if no legal position was found, such as when the material barely fits
+#[must_use]
+pub fn random_position(signature: &str, rng: &mut Rng) -> Option<Position> {
+    let (white, black) = signature
+        .split_once('v')
+        .expect("Material signature needs a 'v'");
+    let mut pieces = parse_side(white, Side::White);
+    pieces.extend(parse_side(black, Side::Black));
+    let pawns = pieces
+        .iter()
+        .filter(|piece| **piece == Piece::WP || **piece == Piece::BP)
+        .count();
+    assert!(pieces.len() <= 64, "Too many pieces");
+    assert!(pawns <= 48, "Too many pawns");
+
+    for _ in 0..MAX_ATTEMPTS {
+        let turn = if rng.below(2) == 0 {
+            Side::White
+        } else {
+            Side::Black
+        };
+        let mut pos = Position {
+            turn,
+            fullmoves: 1,
+            ..Default::default()
+        };
+
+        let mut placed = true;
+        for piece in &pieces {
+            let is_pawn = *piece == Piece::WP || *piece == Piece::BP;
+            let empty = (0..64)
+                .map(Square::from_index)
+                .filter(|sq| pos.get_side_piece_on(*sq).is_none())
+                .filter(|sq| !is_pawn || (1..=6).contains(&sq.y))
+                .collect::<Vec<_>>();
+
+            if empty.is_empty() {
+                placed = false;
+                break;
+            }
+
+            let sq = empty[rng.below(empty.len())];
+            pos.set_piece(*piece, sq);
+            if *piece == Piece::WK {
+                pos.ksq[Side::White as usize] = Some(sq);
+            }
+            if *piece == Piece::BK {
+                pos.ksq[Side::Black as usize] = Some(sq);
+            }
+        }
+
+        pos.refresh_key();
+        let not_to_move = !pos.turn;
+        if placed && !pos.is_attacked(pos.ksq[not_to_move as usize].unwrap(), pos.turn) {
+            return Some(pos);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng_deterministic() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let mut c = Rng::new(43);
+
+        let a = (0..100).map(|_| a.next_u64()).collect::<Vec<_>>();
+        let b = (0..100).map(|_| b.next_u64()).collect::<Vec<_>>();
+        let c = (0..100).map(|_| c.next_u64()).collect::<Vec<_>>();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_below() {
+        let mut rng = Rng::new(1);
+        let mut counts = [0; 3];
+        for _ in 0..30_000 {
+            counts[rng.below(3)] += 1;
+        }
+        assert!(counts.iter().all(|count| (9000..11_000).contains(count)));
+
+        assert_eq!(Rng::new(5).below(1), 0);
+        assert!(Rng::new(5).below(usize::MAX) < usize::MAX);
+    }
+
+    #[test]
+    fn test_random_game() {
+        let start = Position::from_fen("startpos");
+
+        for seed in 0..20 {
+            let game = random_game(&start, 200, &mut Rng::new(seed));
+            assert_eq!(game, random_game(&start, 200, &mut Rng::new(seed)));
+            assert!(game.moves.len() <= 200);
+
+            let end = game.end();
+            if game.moves.len() < 200 {
+                assert!(end.legal_moves().is_empty());
+                assert_ne!(game.result, GameResult::Unknown);
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_position() {
+        let mut rng = Rng::new(7);
+
+        for signature in ["KvK", "KRPvKR", "KQvKNN", "KPPPvKPP", "KBNvK"] {
+            for _ in 0..50 {
+                let pos = random_position(signature, &mut rng).unwrap();
+                let fen = pos.get_fen();
+                let pieces = fen.split(" ").next().unwrap();
+                let count = |c| pieces.chars().filter(|found| *found == c).count();
+                let (white, black) = signature.split_once('v').unwrap();
+
+                for c in "PNBRQK".chars() {
+                    assert_eq!(count(c), white.matches(c).count(), "{fen}");
+                    assert_eq!(
+                        count(c.to_ascii_lowercase()),
+                        black.matches(c).count(),
+                        "{fen}"
+                    );
+                }
+
+                assert!(!pos.is_attacked(pos.ksq[!pos.turn as usize].unwrap(), pos.turn));
+                for x in 0..8 {
+                    for y in [0, 7] {
+                        let piece = pos.get_side_piece_on(Square::from_file_rank(x, y));
+                        assert!(piece != Some(Piece::WP) && piece != Some(Piece::BP));
+                    }
+                }
+                assert_eq!(Position::from_fen(&fen), pos);
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_position_impossible() {
+        // The board is full, so the pawns only fit if every other piece lands on the back ranks
+        let side = format!("K{}{}", "Q".repeat(7), "P".repeat(24));
+        let signature = format!("{side}v{side}");
+        assert_eq!(random_position(&signature, &mut Rng::new(3)), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_random_position_too_many_pawns() {
+        let _ = random_position(&format!("K{}vK", "P".repeat(49)), &mut Rng::new(3));
+    }
+}
@@ -0,0 +1,273 @@
+use crate::{
+    Piece, Side,
+    mv::{Mv, PromoPiece},
+    position::Position,
+    square::Square,
+    tables::side_pieces,
+};
+
+/// The value of each piece type, indexed pawn, knight, bishop, rook, queen, king
+pub type PieceValues = [i32; 6];
+
+/// The piece values used by `Position::see`
+pub static SEE_VALUES: PieceValues = [100, 320, 330, 500, 900, 20000];
+
+const PAWN: usize = 0;
+const QUEEN: usize = 4;
+const KING: usize = 5;
+
+/// Get the index of a piece's type into a `PieceValues` table
+fn piece_type(piece: Piece) -> usize {
+    piece as usize % 6
+}
+
+/// Get the type a move promotes to, if any
+fn promo_type(promo: Option<PromoPiece>) -> Option<usize> {
+    match promo {
+        Some(PromoPiece::Knight) => Some(1),
+        Some(PromoPiece::Bishop) => Some(2),
+        Some(PromoPiece::Rook) => Some(3),
+        Some(PromoPiece::Queen) => Some(QUEEN),
+        None => None,
+    }
+}
+
+/// Turn a piece into another type of the same colour
+fn promote(piece: Piece, piece_type: usize) -> Piece {
+    let side = if (piece as usize) < 6 {
+        Side::White
+    } else {
+        Side::Black
+    };
+    side_pieces(side)[piece_type]
+}
+
+impl Position {
+    /// Static exchange evaluation of a move using the default piece values
+    /// Returns the material the side to move gains from the capture sequence on the destination square
+    #[must_use]
+    pub fn see(&self, mv: &Mv) -> i32 {
+        self.see_with_values(mv, &SEE_VALUES)
+    }
+
+    /// Does the static exchange evaluation of a move reach the threshold?
+    #[must_use]
+    pub fn see_ge(&self, mv: &Mv, threshold: i32) -> bool {
+        self.see_ge_with_values(mv, threshold, &SEE_VALUES)
+    }
+
+    /// Does the static exchange evaluation of a move reach the threshold with the given piece values?
+    /// Stops as soon as the result is known rather than working out the whole exchange
+    #[must_use]
+    pub fn see_ge_with_values(&self, mv: &Mv, threshold: i32, values: &PieceValues) -> bool {
+        let mut board = *self;
+        let (gain, moved) = board.see_first_capture(mv, values);
+
+        // The balance is how far the side that just captured is ahead of what it needs,
+        // and it stays ahead unless the next recapture wins more than that
+        let mut balance = gain - threshold;
+        if balance < 0 {
+            return false;
+        }
+
+        // Still ahead even if the piece is lost for nothing
+        let last_rank = mv.to.y == 0 || mv.to.y == 7;
+        let max_promo_gain = if last_rank {
+            values[QUEEN] - values[PAWN]
+        } else {
+            0
+        };
+        let mut on_square = values[piece_type(moved)];
+        if balance >= on_square + max_promo_gain {
+            return true;
+        }
+
+        let mut side = !self.turn;
+        while let Some((gain, attacker_type)) = board.see_next_capture(mv.to, side, values) {
+            balance = on_square + gain - balance - 1;
+            if balance < 0 {
+                break;
+            }
+            on_square = values[attacker_type];
+            side = !side;
+        }
+
+        // The side that couldn't capture profitably loses
+        side != self.turn
+    }
+
+    /// Static exchange evaluation of a move using the given piece values
+    /// Pins are ignored, and the king only captures if the square is no longer defended
+    #[must_use]
+    pub fn see_with_values(&self, mv: &Mv, values: &PieceValues) -> i32 {
+        let mut board = *self;
+        let (first, moved) = board.see_first_capture(mv, values);
+
+        // There's at most one capture per piece
+        let mut gain = [0; 33];
+        let mut depth = 0;
+        gain[0] = first;
+        let mut on_square = values[piece_type(moved)];
+
+        // Alternate captures with the least valuable attacker, which reveals any x-ray attackers behind it
+        let mut side = !self.turn;
+        while let Some((promo_gain, attacker_type)) = board.see_next_capture(mv.to, side, values) {
+            depth += 1;
+            gain[depth] = on_square + promo_gain - gain[depth - 1];
+            on_square = values[attacker_type];
+            side = !side;
+        }
+
+        // Each side can choose to stop capturing
+        while depth > 0 {
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+            depth -= 1;
+        }
+
+        gain[0]
+    }
+
+    /// Play the initial move of an exchange on the board
+    /// Returns the material gained and the piece left on the destination square
+    fn see_first_capture(&mut self, mv: &Mv, values: &PieceValues) -> (i32, Piece) {
+        let piece = self
+            .get_side_piece_on(mv.from)
+            .expect("No piece on move origin square");
+
+        let is_ep = piece_type(piece) == PAWN && self.ep == Some(mv.to);
+        let captured = if is_ep {
+            values[PAWN]
+        } else {
+            self.get_side_piece_on(mv.to)
+                .map_or(0, |captured| values[piece_type(captured)])
+        };
+        let (gain, moved) = match promo_type(mv.promo) {
+            Some(promo) => (
+                captured + values[promo] - values[PAWN],
+                promote(piece, promo),
+            ),
+            None => (captured, piece),
+        };
+
+        self.clear_square(mv.from);
+        self.set_piece(moved, mv.to);
+        if is_ep {
+            self.clear_square(Square::from_file_rank(mv.to.x, mv.from.y));
+        }
+
+        (gain, moved)
+    }
+
+    /// Recapture on the square with the least valuable attacker of the side, if there is one
+    /// Returns the material gained by promoting and the type of piece now on the square
+    fn see_next_capture(
+        &mut self,
+        sq: Square,
+        side: Side,
+        values: &PieceValues,
+    ) -> Option<(i32, usize)> {
+        let from = self.least_valuable_attacker(sq, side)?;
+        let attacker = self.get_side_piece_on(from).unwrap();
+        let mut attacker_type = piece_type(attacker);
+        let mut promo_gain = 0;
+
+        // Pawns capturing onto the last rank promote to a queen
+        if attacker_type == PAWN && (sq.y == 0 || sq.y == 7) {
+            attacker_type = QUEEN;
+            promo_gain = values[QUEEN] - values[PAWN];
+        }
+
+        self.clear_square(from);
+
+        // The king can't capture a defended piece
+        if attacker_type == KING && self.least_valuable_attacker(sq, !side).is_some() {
+            return None;
+        }
+
+        self.set_piece(promote(attacker, attacker_type), sq);
+        Some((promo_gain, attacker_type))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perft::tests::TESTS;
+
+    #[test]
+    fn test_see() {
+        let tests = [
+            // Undefended pawn
+            (
+                "1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1",
+                "e1e5",
+                100,
+            ),
+            // Defended pawn
+            (
+                "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+                "d3e5",
+                -220,
+            ),
+            // Quiet move to an attacked square
+            ("4k3/8/3p4/8/5N2/8/8/4K3 w - - 0 1", "f4e5", -320),
+            // Quiet move to a safe square
+            ("4k3/8/8/8/5N2/8/8/4K3 w - - 0 1", "f4e6", 0),
+            // X-ray
+            ("4k3/4r3/8/4p3/8/8/4R3/4R1K1 w - - 0 1", "e2e5", 100),
+            ("4k3/4r3/8/4p3/8/8/4R3/6K1 w - - 0 1", "e2e5", -400),
+            ("4k3/4r3/4r3/4p3/8/8/4R3/4R1K1 w - - 0 1", "e2e5", -400),
+            // Defended by the king
+            ("8/8/3k4/4p3/8/8/8/4R1K1 w - - 0 1", "e1e5", -400),
+            // King can capture if the square is undefended
+            ("8/8/3k4/4p3/8/8/4Q3/4R1K1 w - - 0 1", "e2e5", 100),
+            // En passant
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 100),
+            ("4k3/2p5/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 0),
+            // Promotions
+            ("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q", 800),
+            ("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8n", 220),
+            ("2r1k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q", -100),
+            ("2r1k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7c8q", 1300),
+            ("1n2k3/P7/8/8/8/8/8/1R2K3 w - - 0 1", "a7b8q", 1120),
+            // Recapture with promotion
+            ("1N1rk3/P7/8/8/8/8/8/4K3 b - - 0 1", "d8b8", -980),
+        ];
+
+        for (fen, movestr, expected) in tests {
+            let pos = Position::from_fen(fen);
+            let mv = Mv::from_string(movestr);
+            assert_eq!(pos.see(&mv), expected, "{fen} {movestr}");
+            assert!(pos.see_ge(&mv, expected));
+            assert!(!pos.see_ge(&mv, expected + 1));
+        }
+    }
+
+    #[test]
+    fn test_see_ge_perft() {
+        for (fen, _) in TESTS {
+            let pos = Position::from_fen(fen);
+            for mv in pos.legal_moves() {
+                let see = pos.see(&mv);
+                for threshold in [see - 1, see, see + 1, -100, 0, 100] {
+                    assert_eq!(
+                        pos.see_ge(&mv, threshold),
+                        see >= threshold,
+                        "{fen} {mv} {threshold}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_see_values() {
+        let pos = Position::from_fen("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1");
+        let mv = Mv::from_string("d3e5");
+        let values = [100, 300, 300, 500, 900, 20000];
+
+        assert_eq!(pos.see_with_values(&mv, &values), -200);
+        assert!(pos.see_ge_with_values(&mv, -200, &values));
+        assert!(!pos.see_ge_with_values(&mv, -199, &values));
+    }
+}
@@ -0,0 +1,512 @@
+use crate::{
+    Castling, Piece, Side,
+    mv::{Mv, PromoPiece},
+    position::Position,
+    random::{Rng, random_game},
+    square::Square,
+};
+use std::{collections::HashSet, fmt::Display};
+
+/// The first place a self check found something wrong
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct Divergence {
+    /// The FEN of the position where the problem was found
+    pub fen: String,
+    /// The move being checked, if the problem was with a move
+    pub mv: Option<Mv>,
+    /// What went wrong
+    pub reason: String,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.mv {
+            Some(mv) => write!(f, "{} after {} in {}", self.reason, mv, self.fen),
+            None => write!(f, "{} in {}", self.reason, self.fen),
+        }
+    }
+}
+
+/// Does the piece on `from` attack `to`, worked out from scratch without any lookup tables?
+fn brute_force_attacks(pos: &Position, from: Square, to: Square) -> bool {
+    let Some(piece) = pos.get_side_piece_on(from) else {
+        return false;
+    };
+    let dx = to.x as i32 - from.x as i32;
+    let dy = to.y as i32 - from.y as i32;
+
+    // Every square strictly between the two squares must be empty
+    let path_clear = || {
+        let steps = dx.abs().max(dy.abs());
+        (1..steps).all(|i| {
+            let x = from.x as i32 + dx.signum() * i;
+            let y = from.y as i32 + dy.signum() * i;
+            pos.get_side_piece_on(Square::from_file_rank(x as u8, y as u8))
+                .is_none()
+        })
+    };
+    let diagonal = dx != 0 && dx.abs() == dy.abs();
+    let straight = (dx == 0) != (dy == 0);
+
+    match piece {
+        Piece::WP => dx.abs() == 1 && dy == 1,
+        Piece::BP => dx.abs() == 1 && dy == -1,
+        Piece::WN | Piece::BN => {
+            (dx.abs() == 1 && dy.abs() == 2) || (dx.abs() == 2 && dy.abs() == 1)
+        }
+        Piece::WB | Piece::BB => diagonal && path_clear(),
+        Piece::WR | Piece::BR => straight && path_clear(),
+        Piece::WQ | Piece::BQ => (diagonal || straight) && path_clear(),
+        Piece::WK | Piece::BK => (dx, dy) != (0, 0) && dx.abs() <= 1 && dy.abs() <= 1,
+    }
+}
+
+/// Find a side's king by scanning the board
+fn find_king(pos: &Position, side: Side) -> Option<Square> {
+    let king = if side == Side::White {
+        Piece::WK
+    } else {
+        Piece::BK
+    };
+    (0..64)
+        .map(Square::from_index)
+        .find(|sq| pos.get_side_piece_on(*sq) == Some(king))
+}
+
+/// Is the square attacked by the given side, worked out from scratch?
+fn brute_force_attacked(pos: &Position, target: Square, side: Side) -> bool {
+    (0..64)
+        .map(Square::from_index)
+        .filter(|sq| pos.get_side_on(*sq) == Some(side))
+        .any(|sq| brute_force_attacks(pos, sq, target))
+}
+
+/// Is the given side's king attacked, worked out from scratch?
+fn brute_force_in_check(pos: &Position, side: Side) -> bool {
+    let ksq = find_king(pos, side).expect("King not found");
+    brute_force_attacked(pos, ksq, !side)
+}
+
+/// Can the move be played, ignoring whether it leaves the king in check?
+/// Worked out from the rules alone rather than by generating moves
+fn brute_force_pseudolegal(pos: &Position, mv: &Mv) -> bool {
+    let Some(piece) = pos.get_side_piece_on(mv.from) else {
+        return false;
+    };
+    if pos.get_side_on(mv.from) != Some(pos.turn) || pos.get_side_on(mv.to) == Some(pos.turn) {
+        return false;
+    }
+    let dx = mv.to.x as i32 - mv.from.x as i32;
+    let dy = mv.to.y as i32 - mv.from.y as i32;
+
+    match piece {
+        Piece::WP | Piece::BP => {
+            let (forward, start, last) = if piece == Piece::WP {
+                (1, 1, 7)
+            } else {
+                (-1, 6, 0)
+            };
+            if (mv.to.y == last) != mv.promo.is_some() {
+                return false;
+            }
+            let empty = |sq: Square| pos.get_side_piece_on(sq).is_none();
+            let between = Square::from_file_rank(mv.from.x, (mv.from.y as i32 + forward) as u8);
+
+            match (dx.abs(), dy * forward) {
+                (0, 1) => empty(mv.to),
+                (0, 2) => mv.from.y == start && empty(between) && empty(mv.to),
+                (1, 1) => pos.get_side_on(mv.to) == Some(!pos.turn) || pos.ep == Some(mv.to),
+                _ => false,
+            }
+        }
+        _ if mv.promo.is_some() => false,
+        Piece::WK | Piece::BK if dx.abs() == 2 && dy == 0 => {
+            let (kingside, queenside, y) = match pos.turn {
+                Side::White => (Castling::WKS, Castling::WQS, 0),
+                Side::Black => (Castling::BKS, Castling::BQS, 7),
+            };
+            let (perm, rook_x, path) = if dx > 0 {
+                (kingside, 7, 5..7)
+            } else {
+                (queenside, 0, 1..4)
+            };
+            let rook = pos.get_side_piece_on(Square::from_file_rank(rook_x, y));
+            let crossed = [mv.from.x, (mv.from.x + mv.to.x) / 2, mv.to.x];
+
+            mv.from == Square::from_file_rank(4, y)
+                && pos.castling[perm as usize]
+                && rook.is_some()
+                && pos.get_side_on(Square::from_file_rank(rook_x, y)) == Some(pos.turn)
+                && path.into_iter().all(|x| {
+                    pos.get_side_piece_on(Square::from_file_rank(x, y))
+                        .is_none()
+                })
+                && crossed
+                    .into_iter()
+                    .all(|x| !brute_force_attacked(pos, Square::from_file_rank(x, y), !pos.turn))
+        }
+        _ => brute_force_attacks(pos, mv.from, mv.to),
+    }
+}
+
+/// Play a pseudolegal move on a copy of the board, moving only the pieces
+fn brute_force_play(pos: &Position, mv: &Mv) -> Position {
+    let piece = pos.get_side_piece_on(mv.from).expect("No piece to move");
+    let is_pawn = piece == Piece::WP || piece == Piece::BP;
+    let is_king = piece == Piece::WK || piece == Piece::BK;
+    let mut npos = *pos;
+
+    let promoted = match (mv.promo, pos.turn) {
+        (None, _) => piece,
+        (Some(PromoPiece::Knight), Side::White) => Piece::WN,
+        (Some(PromoPiece::Bishop), Side::White) => Piece::WB,
+        (Some(PromoPiece::Rook), Side::White) => Piece::WR,
+        (Some(PromoPiece::Queen), Side::White) => Piece::WQ,
+        (Some(PromoPiece::Knight), Side::Black) => Piece::BN,
+        (Some(PromoPiece::Bishop), Side::Black) => Piece::BB,
+        (Some(PromoPiece::Rook), Side::Black) => Piece::BR,
+        (Some(PromoPiece::Queen), Side::Black) => Piece::BQ,
+    };
+    npos.clear_square(mv.from);
+    npos.set_piece(promoted, mv.to);
+
+    if is_pawn && mv.from.x != mv.to.x && pos.get_side_piece_on(mv.to).is_none() {
+        npos.clear_square(Square::from_file_rank(mv.to.x, mv.from.y));
+    }
+    if is_king && (mv.to.x as i32 - mv.from.x as i32).abs() == 2 {
+        let (rook_from, rook_to) = if mv.to.x == 6 { (7, 5) } else { (0, 3) };
+        let rook = pos
+            .get_side_piece_on(Square::from_file_rank(rook_from, mv.from.y))
+            .expect("No rook to castle with");
+        npos.clear_square(Square::from_file_rank(rook_from, mv.from.y));
+        npos.set_piece(rook, Square::from_file_rank(rook_to, mv.from.y));
+    }
+
+    npos
+}
+
+/// Find every legal move by trying every from, to and promotion combination
+fn brute_force_legal_moves(pos: &Position) -> HashSet<Mv> {
+    let promos = [
+        None,
+        Some(PromoPiece::Knight),
+        Some(PromoPiece::Bishop),
+        Some(PromoPiece::Rook),
+        Some(PromoPiece::Queen),
+    ];
+    let mut moves = HashSet::new();
+
+    for from in (0..64).map(Square::from_index) {
+        if pos.get_side_on(from) != Some(pos.turn) {
+            continue;
+        }
+        for to in (0..64).map(Square::from_index) {
+            for promo in promos {
+                let mv = Mv { from, to, promo };
+                if brute_force_pseudolegal(pos, &mv)
+                    && !brute_force_in_check(&brute_force_play(pos, &mv), pos.turn)
+                {
+                    moves.insert(mv);
+                }
+            }
+        }
+    }
+
+    moves
+}
+
+/// Check the invariants of a single position
+pub fn check_position(pos: &Position) -> Result<(), String> {
+    // King square cache
+    for side in [Side::White, Side::Black] {
+        if pos.ksq[side as usize] != find_king(pos, side) {
+            return Err(format!("{side:?} king square cache is wrong"));
+        }
+    }
+
+    // Incrementally updated hash
+    if pos.hash_key() != pos.hash_key_from_scratch() {
+        return Err("Hash key doesn't match one worked out from scratch".to_string());
+    }
+
+    // Piece counts
+    let pieces = (0..64)
+        .map(|idx| pos.get_side_piece_on(Square::from_index(idx)))
+        .collect::<Vec<_>>();
+    for side in [Side::White, Side::Black] {
+        let count = |piece| pieces.iter().filter(|found| **found == Some(piece)).count();
+        let (pawn, king) = if side == Side::White {
+            (Piece::WP, Piece::WK)
+        } else {
+            (Piece::BP, Piece::BK)
+        };
+        let total = (0..64)
+            .filter(|idx| pos.get_side_on(Square::from_index(*idx)) == Some(side))
+            .count();
+
+        if count(king) != 1 {
+            return Err(format!("{side:?} has {} kings", count(king)));
+        }
+        if count(pawn) > 8 {
+            return Err(format!("{side:?} has {} pawns", count(pawn)));
+        }
+        if total > 16 {
+            return Err(format!("{side:?} has {total} pieces"));
+        }
+    }
+
+    // Pawns on the back ranks
+    for x in 0..8 {
+        for y in [0, 7] {
+            let piece = pos.get_side_piece_on(Square::from_file_rank(x, y));
+            if piece == Some(Piece::WP) || piece == Some(Piece::BP) {
+                return Err(format!("Pawn on {}", Square::from_file_rank(x, y)));
+            }
+        }
+    }
+
+    // The side that just moved can't be in check
+    if brute_force_in_check(pos, !pos.turn) {
+        return Err("Side not to move is in check".to_string());
+    }
+
+    // Castling permissions need the king and rook on their starting squares
+    let castling = [
+        (Castling::WKS, Piece::WK, 4, Piece::WR, 7),
+        (Castling::WQS, Piece::WK, 4, Piece::WR, 0),
+        (Castling::BKS, Piece::BK, 60, Piece::BR, 63),
+        (Castling::BQS, Piece::BK, 60, Piece::BR, 56),
+    ];
+    for (perm, king, ksq, rook, rsq) in castling {
+        if pos.castling[perm as usize]
+            && (pos.get_side_piece_on(Square::from_index(ksq)) != Some(king)
+                || pos.get_side_piece_on(Square::from_index(rsq)) != Some(rook))
+        {
+            return Err(format!("{perm:?} permission without king and rook"));
+        }
+    }
+
+    // The en passant square must be behind a pawn that just double pushed
+    if let Some(ep) = pos.ep {
+        let (rank, pawn, dy) = match pos.turn {
+            Side::White => (5, Piece::BP, -1),
+            Side::Black => (2, Piece::WP, 1),
+        };
+        let pawn_sq = Square::from_file_rank(ep.x, (ep.y as i32 + dy) as u8);
+        let start_sq = Square::from_file_rank(ep.x, (ep.y as i32 - dy) as u8);
+        if ep.y != rank
+            || pos.get_side_piece_on(ep).is_some()
+            || pos.get_side_piece_on(start_sq).is_some()
+            || pos.get_side_piece_on(pawn_sq) != Some(pawn)
+        {
+            return Err(format!("Invalid en passant square {ep}"));
+        }
+    }
+
+    // FEN round trip
+    let fen = pos.get_fen();
+    let parsed = Position::from_fen(&fen);
+    if parsed != *pos || parsed.ksq != pos.ksq || parsed.get_fen() != fen {
+        return Err("FEN round trip failed".to_string());
+    }
+
+    // Binary encoding round trip
+    if Position::decode(&pos.encode()) != Some(*pos) {
+        return Err("Binary encoding round trip failed".to_string());
+    }
+
+    // Staged move generation covers the same moves as the full generator
+    let all = pos.pseudolegal_moves();
+    let staged = pos
+        .captures_and_promotions()
+        .into_iter()
+        .chain(pos.quiet_moves())
+        .collect::<HashSet<_>>();
+    if all.len() != staged.len() || all.iter().collect::<HashSet<_>>() != staged.iter().collect() {
+        return Err("Staged move generation doesn't match".to_string());
+    }
+
+    Ok(())
+}
+
+/// Check that the legal moves are exactly those found by brute force
+fn check_legal_moves(pos: &Position) -> Result<(), String> {
+    // Every legal move is generated, and nothing else
+    let legal = pos.legal_moves();
+    let brute_force = brute_force_legal_moves(pos);
+    if legal.len() != brute_force.len()
+        || legal.iter().copied().collect::<HashSet<_>>() != brute_force
+    {
+        return Err(format!(
+            "Generated {} legal moves, brute force found {}",
+            legal.len(),
+            brute_force.len()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Check a move made from a position against independently recomputed state
+pub fn check_move(pos: &Position, mv: &Mv) -> Result<(), String> {
+    let mut npos = *pos;
+    let legal = npos.makemove(mv);
+
+    // Legality
+    if legal == brute_force_in_check(&npos, pos.turn) {
+        return Err(format!(
+            "makemove says legal is {legal}, brute force disagrees"
+        ));
+    }
+    if !legal {
+        return Ok(());
+    }
+
+    if npos.turn == pos.turn {
+        return Err("Side to move didn't change".to_string());
+    }
+
+    // Halfmoves
+    let piece = pos.get_side_piece_on(mv.from);
+    let is_pawn = piece == Some(Piece::WP) || piece == Some(Piece::BP);
+    let is_capture = pos.get_side_piece_on(mv.to).is_some();
+    let halfmoves = if is_pawn || is_capture {
+        0
+    } else {
+        pos.halfmoves.saturating_add(1)
+    };
+    if npos.halfmoves != halfmoves {
+        return Err(format!(
+            "Halfmoves {} but expected {halfmoves}",
+            npos.halfmoves
+        ));
+    }
+
+    // Fullmoves
+    let fullmoves = pos
+        .fullmoves
+        .saturating_add((pos.turn == Side::Black) as u16);
+    if npos.fullmoves != fullmoves {
+        return Err(format!(
+            "Fullmoves {} but expected {fullmoves}",
+            npos.fullmoves
+        ));
+    }
+
+    // Castling permissions can only be lost
+    for (before, after) in pos.castling.iter().zip(npos.castling) {
+        if after && !before {
+            return Err("Castling permission was gained".to_string());
+        }
+    }
+
+    // The moved piece, or its promotion, ends up on the destination square
+    if npos.get_side_on(mv.to) != Some(pos.turn) || npos.get_side_piece_on(mv.from).is_some() {
+        return Err("Moved piece is in the wrong place".to_string());
+    }
+
+    // Check detection without making the move
+    if pos.gives_check(mv) != brute_force_in_check(&npos, npos.turn) {
+        return Err("gives_check disagrees with brute force".to_string());
+    }
+
+    check_position(&npos)
+}
+
+/// Check every move from every position along a random game
+/// Returns the number of positions checked, or the first divergence found
+pub fn self_check(start: &Position, plies: usize, rng: &mut Rng) -> Result<usize, Divergence> {
+    let game = random_game(start, plies, rng);
+    let mut pos = *start;
+    let mut nodes = 0;
+
+    for played in game.moves.iter().map(Some).chain([None]) {
+        check_position(&pos)
+            .and_then(|()| check_legal_moves(&pos))
+            .map_err(|reason| Divergence {
+                fen: pos.get_fen(),
+                mv: None,
+                reason,
+            })?;
+
+        for mv in pos.pseudolegal_moves() {
+            check_move(&pos, &mv).map_err(|reason| Divergence {
+                fen: pos.get_fen(),
+                mv: Some(mv),
+                reason,
+            })?;
+        }
+        nodes += 1;
+
+        if let Some(mv) = played {
+            let success = pos.makemove(mv);
+            debug_assert!(success);
+        }
+    }
+
+    Ok(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perft::tests::TESTS;
+
+    #[test]
+    fn test_self_check_games() {
+        let start = Position::from_fen("startpos");
+        for seed in 0..5 {
+            let result = self_check(&start, 100, &mut Rng::new(seed));
+            assert!(result.is_ok(), "{}", result.unwrap_err());
+        }
+    }
+
+    #[test]
+    fn test_self_check_perft() {
+        for (seed, (fen, _)) in TESTS.iter().enumerate() {
+            let start = Position::from_fen(fen);
+            let result = self_check(&start, 10, &mut Rng::new(seed as u64));
+            assert!(result.is_ok(), "{}", result.unwrap_err());
+        }
+    }
+
+    #[test]
+    fn test_brute_force_legal_moves() {
+        for (fen, results) in TESTS {
+            let pos = Position::from_fen(fen);
+            assert_eq!(
+                brute_force_legal_moves(&pos).len() as u64,
+                results[0],
+                "{fen}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_self_check_max_counters() {
+        let start = Position::from_fen("4k3/8/8/8/8/8/8/4K2R b K - 65535 65535");
+        let result = self_check(&start, 10, &mut Rng::new(0));
+        assert!(result.is_ok(), "{}", result.unwrap_err());
+    }
+
+    #[test]
+    fn test_detects_divergence() {
+        // Stale king square cache
+        let mut pos = Position::from_fen("startpos");
+        pos.ksq[Side::White as usize] = Some(Square::from_string("e2"));
+        assert!(check_position(&pos).is_err());
+
+        // Castling permission without a rook
+        let pos = Position::from_fen("4k3/8/8/8/8/8/8/4K3 w K - 0 1");
+        assert!(check_position(&pos).is_err());
+
+        // En passant square without a pawn
+        let pos = Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - e6 0 1");
+        assert!(check_position(&pos).is_err());
+
+        let result = self_check(&pos, 10, &mut Rng::new(0));
+        let divergence = result.unwrap_err();
+        assert_eq!(divergence.fen, pos.get_fen());
+        assert_eq!(divergence.mv, None);
+    }
+}
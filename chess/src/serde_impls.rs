@@ -0,0 +1,200 @@
+use crate::{Piece, Side, mv::Mv, position::Position, square::Square};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+/// Is the string a valid square such as "e4"?
+fn is_square(word: &str) -> bool {
+    let bytes = word.as_bytes();
+    bytes.len() == 2 && (b'a'..=b'h').contains(&bytes[0]) && (b'1'..=b'8').contains(&bytes[1])
+}
+
+/// Is the string a valid move such as "e2e4" or "a7a8q"?
+fn is_move(word: &str) -> bool {
+    // Slicing by bytes below needs every character to be one byte
+    if !word.is_ascii() {
+        return false;
+    }
+
+    match word.len() {
+        4 => is_square(&word[0..2]) && is_square(&word[2..4]),
+        5 => {
+            is_square(&word[0..2])
+                && is_square(&word[2..4])
+                && matches!(&word[4..5], "n" | "b" | "r" | "q")
+        }
+        _ => false,
+    }
+}
+
+impl Serialize for Position {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.get_fen())
+    }
+}
+
+impl<'de> Deserialize<'de> for Position {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fen = String::deserialize(deserializer)?;
+        if !Position::is_valid_fen(&fen) {
+            return Err(D::Error::custom(format!("invalid FEN \"{fen}\"")));
+        }
+        Ok(Position::from_fen(&fen))
+    }
+}
+
+impl Serialize for Mv {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Mv {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let movestr = String::deserialize(deserializer)?;
+        if !is_move(&movestr) {
+            return Err(D::Error::custom(format!("invalid move \"{movestr}\"")));
+        }
+        Ok(Mv::from_string(&movestr))
+    }
+}
+
+impl Serialize for Square {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Square {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let word = String::deserialize(deserializer)?;
+        if !is_square(&word) {
+            return Err(D::Error::custom(format!("invalid square \"{word}\"")));
+        }
+        Ok(Square::from_string(&word))
+    }
+}
+
+impl Serialize for Piece {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let c = match self {
+            Piece::WP => "P",
+            Piece::WN => "N",
+            Piece::WB => "B",
+            Piece::WR => "R",
+            Piece::WQ => "Q",
+            Piece::WK => "K",
+            Piece::BP => "p",
+            Piece::BN => "n",
+            Piece::BB => "b",
+            Piece::BR => "r",
+            Piece::BQ => "q",
+            Piece::BK => "k",
+        };
+        serializer.serialize_str(c)
+    }
+}
+
+impl<'de> Deserialize<'de> for Piece {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let word = String::deserialize(deserializer)?;
+        match word.as_str() {
+            "P" => Ok(Piece::WP),
+            "N" => Ok(Piece::WN),
+            "B" => Ok(Piece::WB),
+            "R" => Ok(Piece::WR),
+            "Q" => Ok(Piece::WQ),
+            "K" => Ok(Piece::WK),
+            "p" => Ok(Piece::BP),
+            "n" => Ok(Piece::BN),
+            "b" => Ok(Piece::BB),
+            "r" => Ok(Piece::BR),
+            "q" => Ok(Piece::BQ),
+            "k" => Ok(Piece::BK),
+            _ => Err(D::Error::custom(format!("invalid piece \"{word}\""))),
+        }
+    }
+}
+
+impl Serialize for Side {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Side::White => serializer.serialize_str("w"),
+            Side::Black => serializer.serialize_str("b"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Side {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let word = String::deserialize(deserializer)?;
+        match word.as_str() {
+            "w" => Ok(Side::White),
+            "b" => Ok(Side::Black),
+            _ => Err(D::Error::custom(format!("invalid side \"{word}\""))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let pos = Position::from_fen(fen);
+        let json = serde_json::to_string(&pos).unwrap();
+        assert_eq!(json, format!("\"{fen}\""));
+        assert_eq!(serde_json::from_str::<Position>(&json).unwrap(), pos);
+
+        let invalid = [
+            "\"\"",
+            "\"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1\"",
+            "\"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1\"",
+            "\"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq z9 0 1\"",
+            "\"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0\"",
+        ];
+        for json in invalid {
+            assert!(serde_json::from_str::<Position>(json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn test_move() {
+        for movestr in ["e2e4", "a7a8q", "h2h1n"] {
+            let mv = Mv::from_string(movestr);
+            let json = serde_json::to_string(&mv).unwrap();
+            assert_eq!(json, format!("\"{movestr}\""));
+            assert_eq!(serde_json::from_str::<Mv>(&json).unwrap(), mv);
+        }
+
+        for json in [
+            "\"e2\"",
+            "\"e2e9\"",
+            "\"a7a8k\"",
+            "\"e2e4e5\"",
+            "4",
+            "\"e2eé\"",
+            "\"e2e4é\"",
+            "\"é2e4\"",
+        ] {
+            assert!(serde_json::from_str::<Mv>(json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn test_square_piece_side() {
+        let sq = Square::from_string("c6");
+        assert_eq!(serde_json::to_string(&sq).unwrap(), "\"c6\"");
+        assert_eq!(serde_json::from_str::<Square>("\"c6\"").unwrap(), sq);
+        assert!(serde_json::from_str::<Square>("\"i1\"").is_err());
+        assert!(serde_json::from_str::<Square>("\"é\"").is_err());
+
+        assert_eq!(serde_json::to_string(&Piece::BN).unwrap(), "\"n\"");
+        assert_eq!(serde_json::from_str::<Piece>("\"Q\"").unwrap(), Piece::WQ);
+        assert!(serde_json::from_str::<Piece>("\"x\"").is_err());
+
+        assert_eq!(serde_json::to_string(&Side::Black).unwrap(), "\"b\"");
+        assert_eq!(serde_json::from_str::<Side>("\"w\"").unwrap(), Side::White);
+        assert!(serde_json::from_str::<Side>("\"white\"").is_err());
+    }
+}
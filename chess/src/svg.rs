@@ -0,0 +1,246 @@
+use crate::{Piece, mv::Mv, position::Position, square::Square};
+
+const SQUARE_SIZE: u32 = 45;
+const MARGIN: u32 = 20;
+
+const LIGHT: &str = "#f0d9b5";
+const DARK: &str = "#b58863";
+const HIGHLIGHT: &str = "#cdd16a";
+const ANNOTATION: &str = "#15781b";
+
+/// Options for rendering a position as an SVG diagram
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct SvgOptions {
+    /// Draw the board from black's point of view
+    pub flipped: bool,
+    /// Draw file and rank labels around the board
+    pub coordinates: bool,
+    /// Highlight the origin and destination squares of this move
+    pub last_move: Option<Mv>,
+    /// Arrows to draw, from the first square to the second
+    pub arrows: Vec<(Square, Square)>,
+    /// Squares to circle
+    pub circles: Vec<Square>,
+}
+
+/// The base every piece stands on, in a square of `SQUARE_SIZE`
+const BASE: &str = "M 11 38 L 34 38 L 34 34 L 11 34 Z";
+
+/// The symbol id and outline of each piece type, drawn in a square of `SQUARE_SIZE`
+/// The shapes are defined once in the image and reused, so no fonts are needed
+const PIECE_SHAPES: [(&str, &str); 6] = [
+    (
+        "pawn",
+        "M 17.5 15 A 5 5 0 1 1 27.5 15 A 5 5 0 1 1 17.5 15 Z M 16 34 L 19.5 22 L 25.5 22 L 29 34 Z",
+    ),
+    (
+        "knight",
+        "M 14 34 C 15 28 19 25 19 21 L 14 25 L 11 23 C 12 18 16 13 20 11 L 22 7 L 24 10 C 30 16 31 26 30 34 Z",
+    ),
+    (
+        "bishop",
+        "M 20.5 7 A 2 2 0 1 1 24.5 7 A 2 2 0 1 1 20.5 7 Z M 22.5 9 C 28 14 29 20 27 26 L 18 26 C 16 20 17 14 22.5 9 Z M 16 34 L 18 26 L 27 26 L 29 34 Z",
+    ),
+    (
+        "rook",
+        "M 12 16 L 12 9 L 16 9 L 16 12 L 20 12 L 20 9 L 25 9 L 25 12 L 29 12 L 29 9 L 33 9 L 33 16 Z M 14 34 L 15 16 L 30 16 L 31 34 Z",
+    ),
+    (
+        "queen",
+        "M 11 34 L 9 14 L 15 24 L 17 11 L 22.5 23 L 28 11 L 30 24 L 36 14 L 34 34 Z",
+    ),
+    (
+        "king",
+        "M 21 6 L 24 6 L 24 10 L 28 10 L 28 13 L 24 13 L 24 20 L 21 20 L 21 13 L 17 13 L 17 10 L 21 10 Z M 12 34 C 10 24 16 20 22.5 22 C 29 20 35 24 33 34 Z",
+    ),
+];
+
+/// Get the id of the symbol used to draw a piece
+fn piece_symbol(piece: Piece) -> &'static str {
+    PIECE_SHAPES[piece as usize % 6].0
+}
+
+/// Get the fill and outline colours used to draw a piece
+fn piece_colours(piece: Piece) -> (&'static str, &'static str) {
+    match piece {
+        Piece::WP | Piece::WN | Piece::WB | Piece::WR | Piece::WQ | Piece::WK => {
+            ("#ffffff", "#000000")
+        }
+        Piece::BP | Piece::BN | Piece::BB | Piece::BR | Piece::BQ | Piece::BK => {
+            ("#000000", "#ffffff")
+        }
+    }
+}
+
+/// Get the top left corner of a square in SVG coordinates
+fn square_origin(sq: Square, flipped: bool, offset: u32) -> (u32, u32) {
+    let (col, row) = if flipped {
+        (7 - sq.x as u32, sq.y as u32)
+    } else {
+        (sq.x as u32, 7 - sq.y as u32)
+    };
+    (offset + col * SQUARE_SIZE, offset + row * SQUARE_SIZE)
+}
+
+/// Get the centre of a square in SVG coordinates
+fn square_centre(sq: Square, flipped: bool, offset: u32) -> (u32, u32) {
+    let (x, y) = square_origin(sq, flipped, offset);
+    (x + SQUARE_SIZE / 2, y + SQUARE_SIZE / 2)
+}
+
+impl Position {
+    /// Render the position as a self-contained SVG image
+    #[must_use]
+    pub fn to_svg(&self, options: &SvgOptions) -> String {
+        let offset = if options.coordinates { MARGIN } else { 0 };
+        let size = 8 * SQUARE_SIZE + 2 * offset;
+        let mut svg = String::new();
+
+        svg += &format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{size}\" height=\"{size}\" viewBox=\"0 0 {size} {size}\">"
+        );
+        svg += "<defs><marker id=\"arrowhead\" viewBox=\"0 0 10 10\" refX=\"5\" refY=\"5\" markerWidth=\"3\" markerHeight=\"3\" orient=\"auto\">";
+        svg += &format!("<path d=\"M 0 0 L 10 5 L 0 10 z\" fill=\"{ANNOTATION}\"/>");
+        svg += "</marker>";
+        for (id, shape) in PIECE_SHAPES {
+            svg += &format!(
+                "<symbol id=\"{id}\" viewBox=\"0 0 {SQUARE_SIZE} {SQUARE_SIZE}\"><path d=\"{shape} {BASE}\" stroke-width=\"1.5\" stroke-linejoin=\"round\"/></symbol>"
+            );
+        }
+        svg += "</defs>";
+
+        // Squares
+        for y in 0..8 {
+            for x in 0..8 {
+                let sq = Square::from_file_rank(x, y);
+                let (px, py) = square_origin(sq, options.flipped, offset);
+                let is_highlighted = options
+                    .last_move
+                    .as_ref()
+                    .is_some_and(|mv| mv.from == sq || mv.to == sq);
+                let colour = if is_highlighted {
+                    HIGHLIGHT
+                } else if (x + y) % 2 == 0 {
+                    DARK
+                } else {
+                    LIGHT
+                };
+
+                svg += &format!(
+                    "<rect x=\"{px}\" y=\"{py}\" width=\"{SQUARE_SIZE}\" height=\"{SQUARE_SIZE}\" fill=\"{colour}\"/>"
+                );
+            }
+        }
+
+        // Coordinates
+        if options.coordinates {
+            for i in 0..8 {
+                let sq = Square::from_file_rank(i, i);
+                let (cx, cy) = square_centre(sq, options.flipped, offset);
+                let file = (b'a' + i) as char;
+                let rank = (b'1' + i) as char;
+                let bottom = size - MARGIN / 2;
+                let left = MARGIN / 2;
+
+                svg += &format!(
+                    "<text x=\"{cx}\" y=\"{bottom}\" font-family=\"sans-serif\" font-size=\"12\" text-anchor=\"middle\" dominant-baseline=\"central\">{file}</text>"
+                );
+                svg += &format!(
+                    "<text x=\"{left}\" y=\"{cy}\" font-family=\"sans-serif\" font-size=\"12\" text-anchor=\"middle\" dominant-baseline=\"central\">{rank}</text>"
+                );
+            }
+        }
+
+        // Pieces
+        for y in 0..8 {
+            for x in 0..8 {
+                let sq = Square::from_file_rank(x, y);
+                if let Some(piece) = self.get_side_piece_on(sq) {
+                    let (px, py) = square_origin(sq, options.flipped, offset);
+                    let (fill, stroke) = piece_colours(piece);
+                    let symbol = piece_symbol(piece);
+
+                    svg += &format!(
+                        "<use href=\"#{symbol}\" x=\"{px}\" y=\"{py}\" width=\"{SQUARE_SIZE}\" height=\"{SQUARE_SIZE}\" fill=\"{fill}\" stroke=\"{stroke}\"/>"
+                    );
+                }
+            }
+        }
+
+        // Circles
+        for sq in &options.circles {
+            let (cx, cy) = square_centre(*sq, options.flipped, offset);
+            let r = SQUARE_SIZE / 2 - 3;
+
+            svg += &format!(
+                "<circle cx=\"{cx}\" cy=\"{cy}\" r=\"{r}\" fill=\"none\" stroke=\"{ANNOTATION}\" stroke-width=\"3\" opacity=\"0.8\"/>"
+            );
+        }
+
+        // Arrows
+        for (from, to) in &options.arrows {
+            let (x1, y1) = square_centre(*from, options.flipped, offset);
+            let (x2, y2) = square_centre(*to, options.flipped, offset);
+
+            svg += &format!(
+                "<line x1=\"{x1}\" y1=\"{y1}\" x2=\"{x2}\" y2=\"{y2}\" stroke=\"{ANNOTATION}\" stroke-width=\"7\" stroke-linecap=\"round\" opacity=\"0.8\" marker-end=\"url(#arrowhead)\"/>"
+            );
+        }
+
+        svg += "</svg>";
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_svg_pieces() {
+        let pos = Position::from_fen("startpos");
+        let svg = pos.to_svg(&SvgOptions::default());
+
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.ends_with("</svg>"));
+        assert_eq!(svg.matches("<rect").count(), 64);
+        assert_eq!(svg.matches("<use href=\"#pawn\"").count(), 16);
+        assert_eq!(svg.matches("<use href=\"#king\"").count(), 2);
+        assert_eq!(svg.matches("<symbol").count(), 6);
+
+        // Pieces are drawn without text, so they don't depend on the fonts installed
+        assert!(svg.is_ascii());
+        assert!(!svg.contains("<text"));
+    }
+
+    #[test]
+    fn test_svg_flipped() {
+        let pos = Position::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        let normal = pos.to_svg(&SvgOptions::default());
+        let flipped = pos.to_svg(&SvgOptions {
+            flipped: true,
+            ..Default::default()
+        });
+
+        assert_ne!(normal, flipped);
+        assert_eq!(square_origin(Square::from_string("a1"), false, 0), (0, 315));
+        assert_eq!(square_origin(Square::from_string("a1"), true, 0), (315, 0));
+    }
+
+    #[test]
+    fn test_svg_annotations() {
+        let pos = Position::from_fen("startpos");
+        let svg = pos.to_svg(&SvgOptions {
+            coordinates: true,
+            last_move: Some(Mv::from_string("e2e4")),
+            arrows: vec![(Square::from_string("g1"), Square::from_string("f3"))],
+            circles: vec![Square::from_string("d5"), Square::from_string("e5")],
+            ..Default::default()
+        });
+
+        assert_eq!(svg.matches(HIGHLIGHT).count(), 2);
+        assert_eq!(svg.matches("<line").count(), 1);
+        assert_eq!(svg.matches("<circle").count(), 2);
+        assert_eq!(svg.matches("font-family").count(), 16);
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{Piece, Side};
+
+/// Knight move offsets
+pub(crate) static KNIGHT: [(i32, i32); 8] = [
+    (-1, 2),
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (-1, -2),
+    (1, -2),
+    (-2, -1),
+    (-2, 1),
+];
+
+/// Bishop directions
+pub(crate) static BISHOP: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+
+/// Rook directions
+pub(crate) static ROOK: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+/// Queen directions
+pub(crate) static QUEEN: [(i32, i32); 8] = [
+    (1, 1),
+    (1, -1),
+    (-1, -1),
+    (-1, 1),
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+];
+
+/// King move offsets
+pub(crate) static KING: [(i32, i32); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+/// The pawn, knight, bishop, rook, queen and king of a side
+pub(crate) fn side_pieces(side: Side) -> [Piece; 6] {
+    match side {
+        Side::White => [
+            Piece::WP,
+            Piece::WN,
+            Piece::WB,
+            Piece::WR,
+            Piece::WQ,
+            Piece::WK,
+        ],
+        Side::Black => [
+            Piece::BP,
+            Piece::BN,
+            Piece::BB,
+            Piece::BR,
+            Piece::BQ,
+            Piece::BK,
+        ],
+    }
+}
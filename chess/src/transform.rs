@@ -0,0 +1,148 @@
+use crate::{Castling, Piece, Side, position::Position, square::Square};
+
+/// Get the same piece type of the other colour
+fn swap_colour(piece: Piece) -> Piece {
+    match piece {
+        Piece::WP => Piece::BP,
+        Piece::WN => Piece::BN,
+        Piece::WB => Piece::BB,
+        Piece::WR => Piece::BR,
+        Piece::WQ => Piece::BQ,
+        Piece::WK => Piece::BK,
+        Piece::BP => Piece::WP,
+        Piece::BN => Piece::WN,
+        Piece::BB => Piece::WB,
+        Piece::BR => Piece::WR,
+        Piece::BQ => Piece::WQ,
+        Piece::BK => Piece::WK,
+    }
+}
+
+/// Flip a square vertically
+fn mirror_square(sq: Square) -> Square {
+    Square::from_file_rank(sq.x, 7 - sq.y)
+}
+
+/// Flip a square horizontally
+fn flip_square(sq: Square) -> Square {
+    Square::from_file_rank(7 - sq.x, sq.y)
+}
+
+impl Position {
+    /// Get the position with the ranks flipped and the colours swapped
+    /// The mirrored position is the same game from the other side's point of view
+    #[must_use]
+    pub fn mirrored(&self) -> Self {
+        let mut pos = *self;
+
+        for x in 0..8 {
+            for y in 0..8 {
+                let sq = Square::from_file_rank(x, y);
+                match self.get_side_piece_on(mirror_square(sq)) {
+                    Some(piece) => pos.set_piece(swap_colour(piece), sq),
+                    None => pos.clear_square(sq),
+                }
+            }
+        }
+
+        pos.turn = !self.turn;
+        pos.castling[Castling::WKS as usize] = self.castling[Castling::BKS as usize];
+        pos.castling[Castling::WQS as usize] = self.castling[Castling::BQS as usize];
+        pos.castling[Castling::BKS as usize] = self.castling[Castling::WKS as usize];
+        pos.castling[Castling::BQS as usize] = self.castling[Castling::WQS as usize];
+        pos.ep = self.ep.map(mirror_square);
+        pos.ksq[Side::White as usize] = self.ksq[Side::Black as usize].map(mirror_square);
+        pos.ksq[Side::Black as usize] = self.ksq[Side::White as usize].map(mirror_square);
+        pos.refresh_key();
+
+        pos
+    }
+
+    /// Get the position with the files flipped
+    /// Castling isn't symmetrical, so the position must not have any castling permissions
+    #[must_use]
+    pub fn flipped_horizontally(&self) -> Self {
+        assert!(
+            self.castling.iter().all(|allowed| !allowed),
+            "Can't flip a position with castling permissions"
+        );
+
+        let mut pos = *self;
+
+        for x in 0..8 {
+            for y in 0..8 {
+                let sq = Square::from_file_rank(x, y);
+                match self.get_side_piece_on(flip_square(sq)) {
+                    Some(piece) => pos.set_piece(piece, sq),
+                    None => pos.clear_square(sq),
+                }
+            }
+        }
+
+        pos.ep = self.ep.map(flip_square);
+        pos.ksq = self.ksq.map(|ksq| ksq.map(flip_square));
+
+        pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perft::{perft, tests::TESTS};
+
+    #[test]
+    fn test_mirrored() {
+        let tests = [
+            (
+                "startpos",
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1",
+            ),
+            (
+                "r3k3/8/8/8/4Pp2/8/8/4K2R b Kq e3 3 20",
+                "4k2r/8/8/4pP2/8/8/8/R3K3 w Qk e6 3 20",
+            ),
+        ];
+
+        for (fen, expected) in tests {
+            let pos = Position::from_fen(fen);
+            assert_eq!(pos.mirrored().get_fen(), expected);
+            assert_eq!(pos.mirrored().mirrored(), pos);
+        }
+    }
+
+    #[test]
+    fn test_flipped_horizontally() {
+        let pos = Position::from_fen("4k3/8/8/8/2pP4/8/B7/3K4 b - d3 0 1");
+        assert_eq!(
+            pos.flipped_horizontally().get_fen(),
+            "3k4/8/8/8/4Pp2/8/7B/4K3 b - e3 0 1"
+        );
+        assert_eq!(pos.flipped_horizontally().flipped_horizontally(), pos);
+    }
+
+    #[test]
+    fn test_perft_symmetry() {
+        for (fen, _) in TESTS {
+            let pos = Position::from_fen(fen);
+            let mirrored = pos.mirrored();
+            let flipped = pos
+                .castling
+                .iter()
+                .all(|allowed| !allowed)
+                .then(|| pos.flipped_horizontally());
+
+            for depth in 1..=3 {
+                let nodes = perft(&pos, depth);
+                assert_eq!(
+                    perft(&mirrored, depth),
+                    nodes,
+                    "mirrored {fen} depth {depth}"
+                );
+                if let Some(flipped) = &flipped {
+                    assert_eq!(perft(flipped, depth), nodes, "flipped {fen} depth {depth}");
+                }
+            }
+        }
+    }
+}
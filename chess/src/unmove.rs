@@ -0,0 +1,478 @@
+use crate::{
+    Castling, Piece, Side,
+    mv::{Mv, PromoPiece},
+    position::Position,
+    square::Square,
+    tables::{BISHOP, KING, KNIGHT, QUEEN, ROOK, side_pieces},
+    zobrist::{castling_key, side_key},
+};
+
+/// The castling permissions, with the king and rook squares each depends on
+static CASTLING_SQUARES: [(Castling, u8, u8); 4] = [
+    (Castling::WKS, 4, 7),
+    (Castling::WQS, 4, 0),
+    (Castling::BKS, 60, 63),
+    (Castling::BQS, 60, 56),
+];
+
+/// A move played backwards
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct Unmove {
+    /// The forward move being taken back
+    pub mv: Mv,
+    /// The piece the forward move captured, which is put back
+    pub captured: Option<Piece>,
+    /// Was the forward move an en passant capture?
+    pub en_passant: bool,
+    /// The castling permissions before the forward move
+    pub castling: [bool; 4],
+}
+
+/// Get the square offset from another, if it's on the board
+fn offset(sq: Square, dx: i32, dy: i32) -> Option<Square> {
+    let nx = sq.x as i32 + dx;
+    let ny = sq.y as i32 + dy;
+    if (0..8).contains(&nx) && (0..8).contains(&ny) {
+        Some(Square::from_file_rank(nx as u8, ny as u8))
+    } else {
+        None
+    }
+}
+
+impl Position {
+    /// Generate the moves that could have led to this position
+    /// This includes uncaptures of every piece type, unpromotions, un-en passant and uncastling.
+    /// The predecessor positions may be illegal, which `unmake` reports.
+    #[must_use]
+    pub fn pseudolegal_unmoves(&self) -> Vec<Unmove> {
+        let us = !self.turn;
+        let [pawn, knight, bishop, rook, queen, king] = side_pieces(us);
+        let theirs = side_pieces(self.turn);
+        let forwards = if us == Side::White { 1 } else { -1 };
+        let last_rank = if us == Side::White { 7 } else { 0 };
+        let mut base = vec![];
+
+        // An en passant square means the last move was a double pawn push
+        if let Some(ep) = self.ep {
+            if let Some(to) = offset(ep, 0, forwards)
+                && let Some(from) = offset(ep, 0, -forwards)
+                && self.get_side_piece_on(to) == Some(pawn)
+                && self.get_side_piece_on(from).is_none()
+            {
+                base.push((
+                    Mv {
+                        from,
+                        to,
+                        promo: None,
+                    },
+                    None,
+                    false,
+                ));
+            }
+            return self.with_castling_variants(base);
+        }
+
+        for idx in 0..64 {
+            let to = Square::from_index(idx);
+            let Some(piece) = self.get_side_piece_on(to) else {
+                continue;
+            };
+            if self.get_side_on(to) != Some(us) {
+                continue;
+            }
+
+            // Pieces that could be put back on the destination square
+            let mut uncaptures = vec![None];
+            uncaptures.extend(theirs[1..5].iter().copied().map(Some));
+            if (1..=6).contains(&to.y) {
+                uncaptures.push(Some(theirs[0]));
+            }
+
+            // Pawn moves, or promotions back to a pawn
+            if piece == pawn || (to.y == last_rank && piece != king) {
+                let promo = if piece == pawn {
+                    None
+                } else {
+                    Some(match piece {
+                        _ if piece == knight => PromoPiece::Knight,
+                        _ if piece == bishop => PromoPiece::Bishop,
+                        _ if piece == rook => PromoPiece::Rook,
+                        _ => PromoPiece::Queen,
+                    })
+                };
+
+                for dx in [-1, 0, 1] {
+                    let Some(from) = offset(to, dx, -forwards) else {
+                        continue;
+                    };
+                    if self.get_side_piece_on(from).is_some() || !(1..=6).contains(&from.y) {
+                        continue;
+                    }
+
+                    let mv = Mv { from, to, promo };
+                    if dx == 0 {
+                        base.push((mv, None, false));
+                        continue;
+                    }
+
+                    for captured in uncaptures.iter().skip(1) {
+                        base.push((mv, *captured, false));
+                    }
+
+                    // En passant, where the captured pawn was beside the origin square
+                    let pawn_from = offset(to, 0, forwards);
+                    let pawn_to = offset(to, 0, -forwards);
+                    if piece == pawn
+                        && to.y == if us == Side::White { 5 } else { 2 }
+                        && pawn_from.is_some_and(|sq| self.get_side_piece_on(sq).is_none())
+                        && pawn_to.is_some_and(|sq| self.get_side_piece_on(sq).is_none())
+                    {
+                        base.push((mv, Some(theirs[0]), true));
+                    }
+                }
+            }
+
+            if piece == pawn {
+                continue;
+            }
+
+            // Piece moves, which are their own reverse
+            let (dirs, sliding): (&[(i32, i32)], bool) = match piece {
+                _ if piece == knight => (&KNIGHT, false),
+                _ if piece == bishop => (&BISHOP, true),
+                _ if piece == rook => (&ROOK, true),
+                _ if piece == queen => (&QUEEN, true),
+                _ => (&KING, false),
+            };
+            for (dx, dy) in dirs {
+                let mut from = offset(to, *dx, *dy);
+                while let Some(sq) = from {
+                    if self.get_side_piece_on(sq).is_some() {
+                        break;
+                    }
+
+                    let mv = Mv {
+                        from: sq,
+                        to,
+                        promo: None,
+                    };
+                    for captured in &uncaptures {
+                        base.push((mv, *captured, false));
+                    }
+
+                    if !sliding {
+                        break;
+                    }
+                    from = offset(sq, *dx, *dy);
+                }
+            }
+
+            // Uncastling
+            let home = if us == Side::White { 0 } else { 7 };
+            if piece == king && to.y == home {
+                for (king_to, rook_to, empty) in [(6, 5, &[4, 7][..]), (2, 3, &[0, 1, 4][..])] {
+                    if to.x == king_to
+                        && self.get_side_piece_on(Square::from_file_rank(rook_to, home))
+                            == Some(rook)
+                        && empty.iter().all(|x| {
+                            self.get_side_piece_on(Square::from_file_rank(*x, home))
+                                .is_none()
+                        })
+                    {
+                        let mv = Mv {
+                            from: Square::from_file_rank(4, home),
+                            to,
+                            promo: None,
+                        };
+                        base.push((mv, None, false));
+                    }
+                }
+            }
+        }
+
+        self.with_castling_variants(base)
+    }
+
+    /// Expand each unmove into every set of castling permissions the predecessor could have had
+    fn with_castling_variants(&self, base: Vec<(Mv, Option<Piece>, bool)>) -> Vec<Unmove> {
+        let mut unmoves = vec![];
+
+        for (mv, captured, en_passant) in base {
+            let touches = |perm: &(Castling, u8, u8)| {
+                let (_, ksq, rsq) = *perm;
+                [ksq, rsq].map(Square::from_index).contains(&mv.from)
+                    || [ksq, rsq].map(Square::from_index).contains(&mv.to)
+            };
+
+            // The forward move would have removed any permission it touched
+            if CASTLING_SQUARES
+                .iter()
+                .any(|perm| self.castling[perm.0 as usize] && touches(perm))
+            {
+                continue;
+            }
+
+            let mut unmove = Unmove {
+                mv,
+                captured,
+                en_passant,
+                castling: self.castling,
+            };
+            let mut predecessor = *self;
+            let _ = predecessor.unmake(&unmove);
+
+            // Permissions the forward move could have removed
+            let restorable = CASTLING_SQUARES
+                .iter()
+                .filter(|perm| touches(perm))
+                .filter(|(perm, ksq, rsq)| {
+                    let [_, _, _, rook, _, king] = side_pieces(if (*perm as usize) < 2 {
+                        Side::White
+                    } else {
+                        Side::Black
+                    });
+                    predecessor.get_side_piece_on(Square::from_index(*ksq)) == Some(king)
+                        && predecessor.get_side_piece_on(Square::from_index(*rsq)) == Some(rook)
+                })
+                .map(|(perm, _, _)| *perm as usize)
+                .collect::<Vec<_>>();
+
+            // Castling needs the permission it used, and the king can't castle out of or through check
+            let is_castling = (mv.from == Square::from_index(4)
+                || mv.from == Square::from_index(60))
+                && self
+                    .get_side_piece_on(mv.to)
+                    .is_some_and(|piece| piece == Piece::WK || piece == Piece::BK)
+                && (mv.from.x as i32 - mv.to.x as i32).abs() == 2;
+            let required = if is_castling {
+                let perm = match (mv.to.y, mv.to.x) {
+                    (0, 6) => Castling::WKS,
+                    (0, _) => Castling::WQS,
+                    (_, 6) => Castling::BKS,
+                    (_, _) => Castling::BQS,
+                } as usize;
+                let passes = [mv.from.x, (mv.from.x + mv.to.x) / 2, mv.to.x]
+                    .map(|x| Square::from_file_rank(x, mv.from.y));
+                if !restorable.contains(&perm)
+                    || passes
+                        .iter()
+                        .any(|sq| predecessor.is_attacked(*sq, self.turn))
+                {
+                    continue;
+                }
+                Some(perm)
+            } else {
+                None
+            };
+
+            for subset in 0..(1 << restorable.len()) {
+                let mut castling = self.castling;
+                for (i, perm) in restorable.iter().enumerate() {
+                    castling[*perm] = subset & (1 << i) != 0;
+                }
+                if required.is_some_and(|perm| !castling[perm]) {
+                    continue;
+                }
+                unmove.castling = castling;
+                unmoves.push(unmove);
+            }
+        }
+
+        unmoves
+    }
+
+    /// Take back a move produced by `pseudolegal_unmoves`
+    /// Returns whether the predecessor position is legal.
+    /// The predecessor's en passant square is only known after un-en passant,
+    /// and its halfmoves are only known if the move was reversible.
+    pub fn unmake(&mut self, unmove: &Unmove) -> bool {
+        let us = !self.turn;
+        let [pawn, _, _, rook, _, king] = side_pieces(us);
+        let mv = &unmove.mv;
+        let piece = self
+            .get_side_piece_on(mv.to)
+            .expect("No piece on unmove square");
+
+        // Put the piece back, as a pawn if it promoted
+        self.clear_square(mv.to);
+        let moved = if mv.promo.is_some() { pawn } else { piece };
+        self.set_piece(moved, mv.from);
+
+        // Put back the captured piece
+        if let Some(captured) = unmove.captured {
+            if unmove.en_passant {
+                self.set_piece(captured, Square::from_file_rank(mv.to.x, mv.from.y));
+            } else {
+                self.set_piece(captured, mv.to);
+            }
+        }
+
+        // Uncastling also moves the rook back
+        if moved == king && (mv.from.x as i32 - mv.to.x as i32).abs() == 2 {
+            let (rook_from, rook_to) = if mv.to.x == 6 { (7, 5) } else { (0, 3) };
+            self.clear_square(Square::from_file_rank(rook_to, mv.from.y));
+            self.set_piece(rook, Square::from_file_rank(rook_from, mv.from.y));
+        }
+
+        if moved == king {
+            self.ksq[us as usize] = Some(mv.from);
+        }
+
+        self.key ^= castling_key(&self.castling) ^ castling_key(&unmove.castling);
+        self.castling = unmove.castling;
+        self.ep = if unmove.en_passant { Some(mv.to) } else { None };
+        self.halfmoves = if moved == pawn || unmove.captured.is_some() {
+            0
+        } else {
+            self.halfmoves.saturating_sub(1)
+        };
+        if us == Side::Black {
+            self.fullmoves = self.fullmoves.saturating_sub(1);
+        }
+        self.turn = us;
+        self.key ^= side_key(Side::Black);
+
+        // The side not to move in the predecessor can't be in check
+        !self.is_attacked(self.ksq[!us as usize].expect("King not found"), us)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perft::tests::TESTS;
+
+    /// Every perft position, plus every position one legal move later
+    fn positions() -> Vec<Position> {
+        let mut positions = vec![];
+        for (fen, _) in TESTS {
+            let pos = Position::from_fen(fen);
+            positions.push(pos);
+            for mv in pos.legal_moves() {
+                let mut npos = pos;
+                let _ = npos.makemove(&mv);
+                positions.push(npos);
+            }
+        }
+        positions
+    }
+
+    #[test]
+    fn test_forward_moves_are_unmoves() {
+        for (fen, _) in TESTS {
+            let pos = Position::from_fen(fen);
+            for mv in pos.legal_moves() {
+                let mut npos = pos;
+                let _ = npos.makemove(&mv);
+
+                let piece = pos.get_side_piece_on(mv.from);
+                let en_passant =
+                    (piece == Some(Piece::WP) || piece == Some(Piece::BP)) && pos.ep == Some(mv.to);
+                let captured = if en_passant {
+                    pos.get_side_piece_on(Square::from_file_rank(mv.to.x, mv.from.y))
+                } else {
+                    pos.get_side_piece_on(mv.to)
+                };
+                let unmove = Unmove {
+                    mv,
+                    captured,
+                    en_passant,
+                    castling: pos.castling,
+                };
+
+                assert!(npos.pseudolegal_unmoves().contains(&unmove), "{fen} {mv}");
+
+                let mut predecessor = npos;
+                assert!(predecessor.unmake(&unmove), "{fen} {mv}");
+                assert_eq!(predecessor.board, pos.board, "{fen} {mv}");
+                assert_eq!(predecessor.ksq, pos.ksq, "{fen} {mv}");
+                assert_eq!(predecessor.turn, pos.turn, "{fen} {mv}");
+                assert_eq!(predecessor.castling, pos.castling, "{fen} {mv}");
+                assert_eq!(
+                    predecessor.hash_key(),
+                    predecessor.hash_key_from_scratch(),
+                    "{fen} {mv}"
+                );
+                if en_passant {
+                    assert_eq!(predecessor.ep, pos.ep, "{fen} {mv}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_unmoves_are_forward_moves() {
+        for pos in positions() {
+            for unmove in pos.pseudolegal_unmoves() {
+                let mut predecessor = pos;
+                if !predecessor.unmake(&unmove) {
+                    continue;
+                }
+
+                let fen = pos.get_fen();
+                let mut npos = predecessor;
+                assert!(
+                    predecessor.pseudolegal_moves().contains(&unmove.mv),
+                    "{fen} {unmove:?}"
+                );
+                assert!(npos.makemove(&unmove.mv), "{fen} {unmove:?}");
+                assert_eq!(npos.board, pos.board, "{fen} {unmove:?}");
+                assert_eq!(npos.castling, pos.castling, "{fen} {unmove:?}");
+                assert_eq!(npos.ep, pos.ep, "{fen} {unmove:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_unmoves() {
+        // Uncastling, with the queen side permission possibly lost earlier
+        let pos = Position::from_fen("4k3/8/8/8/8/8/8/R4RK1 b - - 1 1");
+        let uncastles = pos
+            .pseudolegal_unmoves()
+            .into_iter()
+            .filter(|unmove| unmove.mv == Mv::from_string("e1g1"))
+            .map(|unmove| unmove.castling)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            uncastles,
+            vec![[true, false, false, false], [true, true, false, false]]
+        );
+
+        // A double push is the only way to get an en passant square
+        let pos = Position::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1");
+        let unmoves = pos.pseudolegal_unmoves();
+        assert_eq!(unmoves.len(), 1);
+        assert_eq!(unmoves[0].mv, Mv::from_string("e2e4"));
+
+        // Unpromotion, with and without an uncapture
+        let pos = Position::from_fen("1Q2k3/8/8/8/8/8/8/4K3 b - - 0 1");
+        let unmoves = pos.pseudolegal_unmoves();
+        for (movestr, captured) in [
+            ("b7b8q", None),
+            ("a7b8q", Some(Piece::BR)),
+            ("c7b8q", Some(Piece::BN)),
+        ] {
+            let unmove = Unmove {
+                mv: Mv::from_string(movestr),
+                captured,
+                en_passant: false,
+                castling: [false; 4],
+            };
+            assert!(unmoves.contains(&unmove), "{movestr}");
+        }
+
+        // Un-en passant
+        let pos = Position::from_fen("4k3/8/3P4/8/8/8/8/4K3 b - - 0 1");
+        let unmove = Unmove {
+            mv: Mv::from_string("e5d6"),
+            captured: Some(Piece::BP),
+            en_passant: true,
+            castling: [false; 4],
+        };
+        assert!(pos.pseudolegal_unmoves().contains(&unmove));
+
+        let mut predecessor = pos;
+        assert!(predecessor.unmake(&unmove));
+        assert_eq!(predecessor.get_fen(), "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+    }
+}
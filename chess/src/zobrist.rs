@@ -0,0 +1,278 @@
+use crate::{Piece, Side, mv::Mv, position::Position, square::Square};
+
+/// Random keys for each part of the position
+struct Keys {
+    pieces: [[u64; 64]; 12],
+    side: u64,
+    castling: [u64; 4],
+    ep_file: [u64; 8],
+}
+
+/// The SplitMix64 step used by `random::Rng`, usable at compile time
+const fn splitmix(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+/// Generate the keys from a fixed seed
+const fn generate_keys() -> Keys {
+    let mut keys = Keys {
+        pieces: [[0; 64]; 12],
+        side: 0,
+        castling: [0; 4],
+        ep_file: [0; 8],
+    };
+    let mut state = 0x5EED;
+    let mut key;
+
+    let mut piece = 0;
+    while piece < 12 {
+        let mut idx = 0;
+        while idx < 64 {
+            (state, key) = splitmix(state);
+            keys.pieces[piece][idx] = key;
+            idx += 1;
+        }
+        piece += 1;
+    }
+
+    (state, keys.side) = splitmix(state);
+
+    let mut idx = 0;
+    while idx < 4 {
+        (state, key) = splitmix(state);
+        keys.castling[idx] = key;
+        idx += 1;
+    }
+
+    let mut idx = 0;
+    while idx < 8 {
+        (state, key) = splitmix(state);
+        keys.ep_file[idx] = key;
+        idx += 1;
+    }
+
+    keys
+}
+
+static KEYS: Keys = generate_keys();
+
+/// Get the key for a piece on a square
+pub(crate) fn piece_key(piece: Piece, sq: Square) -> u64 {
+    KEYS.pieces[piece as usize][(sq.y * 8 + sq.x) as usize]
+}
+
+/// Get the key for the side to move
+pub(crate) fn side_key(side: Side) -> u64 {
+    if side == Side::Black { KEYS.side } else { 0 }
+}
+
+/// Get the combined key for a set of castling permissions
+pub(crate) fn castling_key(castling: &[bool; 4]) -> u64 {
+    castling
+        .iter()
+        .zip(KEYS.castling)
+        .filter(|(allowed, _)| **allowed)
+        .fold(0, |key, (_, perm)| key ^ perm)
+}
+
+impl Position {
+    /// Get the Zobrist hash of the position
+    /// Positions that are equal ignoring the move counters have the same hash,
+    /// so the en passant square only counts if an en passant capture is legal
+    #[must_use]
+    pub fn hash_key(&self) -> u64 {
+        self.key ^ self.ep_key()
+    }
+
+    /// Get the Zobrist hash of the position, worked out from scratch
+    /// This is much slower than `hash_key`, and only useful to check it against
+    #[must_use]
+    pub fn hash_key_from_scratch(&self) -> u64 {
+        let mut key = self.board_key();
+
+        if let Some(ep) = self.ep
+            && self.has_legal_ep()
+        {
+            key ^= KEYS.ep_file[ep.x as usize];
+        }
+
+        key
+    }
+
+    /// Recompute the stored key after the position was changed without updating it
+    pub fn refresh_key(&mut self) {
+        self.key = self.board_key();
+    }
+
+    /// Get the key for the pieces, side to move and castling permissions from scratch
+    pub(crate) fn board_key(&self) -> u64 {
+        let mut key = 0;
+
+        for idx in 0..64 {
+            let sq = Square::from_index(idx);
+            if let Some(piece) = self.get_side_piece_on(sq) {
+                key ^= piece_key(piece, sq);
+            }
+        }
+
+        key ^ side_key(self.turn) ^ castling_key(&self.castling)
+    }
+
+    /// Get the key for the en passant square, if an en passant capture is legal
+    /// The en passant key isn't stored, as whether it counts changes with the rest of the position,
+    /// but only the pawns next to the pawn that just moved need checking
+    fn ep_key(&self) -> u64 {
+        let Some(ep) = self.ep else {
+            return 0;
+        };
+        let (pawn, pawn_y) = match self.turn {
+            Side::White => (Piece::WP, ep.y.wrapping_sub(1)),
+            Side::Black => (Piece::BP, ep.y + 1),
+        };
+
+        let legal = [ep.x.wrapping_sub(1), ep.x + 1]
+            .into_iter()
+            .filter(|x| *x < 8 && pawn_y < 8)
+            .map(|x| Square::from_file_rank(x, pawn_y))
+            .filter(|from| self.get_side_piece_on(*from) == Some(pawn))
+            .any(|from| {
+                let mut npos = *self;
+                npos.makemove(&Mv {
+                    from,
+                    to: ep,
+                    promo: None,
+                })
+            });
+
+        if legal {
+            KEYS.ep_file[ep.x as usize]
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::perft::tests::TESTS;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_transposition() {
+        let mut a = Position::from_fen("startpos");
+        let mut b = Position::from_fen("startpos");
+        for movestr in ["g1f3", "g8f6", "b1c3", "b8c6"] {
+            assert!(a.makemove(&Mv::from_string(movestr)));
+        }
+        for movestr in ["b1c3", "b8c6", "g1f3", "g8f6"] {
+            assert!(b.makemove(&Mv::from_string(movestr)));
+        }
+        assert_eq!(a.hash_key(), b.hash_key());
+
+        // The move counters don't matter
+        let mut c = Position::from_fen("startpos");
+        for movestr in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+            assert!(c.makemove(&Mv::from_string(movestr)));
+        }
+        assert_eq!(c.hash_key(), Position::from_fen("startpos").hash_key());
+    }
+
+    #[test]
+    fn test_differences() {
+        let white = Position::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1");
+        let black = Position::from_fen("4k3/8/8/8/8/8/8/R3K3 b Q - 0 1");
+        let no_castling = Position::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        assert_ne!(white.hash_key(), black.hash_key());
+        assert_ne!(white.hash_key(), no_castling.hash_key());
+
+        // The en passant square only counts if the capture is legal
+        let legal = Position::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        let no_ep = Position::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1");
+        assert_ne!(legal.hash_key(), no_ep.hash_key());
+
+        let pointless = Position::from_fen("4k3/8/8/3p4/8/8/8/4K3 w - d6 0 1");
+        let no_ep = Position::from_fen("4k3/8/8/3p4/8/8/8/4K3 w - - 0 1");
+        assert_eq!(pointless.hash_key(), no_ep.hash_key());
+    }
+
+    #[test]
+    fn test_incremental() {
+        for (fen, _) in TESTS {
+            let pos = Position::from_fen(fen);
+            assert_eq!(pos.hash_key(), pos.hash_key_from_scratch(), "{fen}");
+
+            for mv in pos.pseudolegal_moves() {
+                let mut npos = pos;
+                let _ = npos.makemove(&mv);
+                assert_eq!(npos.hash_key(), npos.hash_key_from_scratch(), "{fen} {mv}");
+
+                if let Some(undo) = npos.make_null_move() {
+                    assert_eq!(npos.hash_key(), npos.hash_key_from_scratch(), "{fen} {mv}");
+                    npos.unmake_null_move(undo);
+                    assert_eq!(npos.hash_key(), npos.hash_key_from_scratch(), "{fen} {mv}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_refresh_key() {
+        let mut pos = Position::from_fen("startpos");
+        pos.turn = Side::Black;
+        pos.castling = [false; 4];
+        pos.board[4][1] = None;
+        assert_ne!(pos.hash_key(), pos.hash_key_from_scratch());
+
+        pos.refresh_key();
+        assert_eq!(pos.hash_key(), pos.hash_key_from_scratch());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Stale Zobrist key")]
+    fn test_makemove_stale_key() {
+        let mut pos = Position::from_fen("startpos");
+        pos.castling = [false; 4];
+        let _ = pos.makemove(&Mv::from_string("e2e4"));
+    }
+
+    #[test]
+    fn test_matches_equality() {
+        let mut positions = vec![];
+        for (fen, _) in TESTS {
+            let pos = Position::from_fen(fen);
+            positions.push(pos);
+            for mv in pos.legal_moves() {
+                let mut npos = pos;
+                assert!(npos.makemove(&mv));
+                positions.push(npos);
+            }
+        }
+
+        let mut keys = HashSet::new();
+        for (idx, pos) in positions.iter().enumerate() {
+            for other in &positions[..idx] {
+                if pos.eq_ignoring_counters(other) {
+                    assert_eq!(pos.hash_key(), other.hash_key());
+                }
+            }
+            keys.insert(pos.hash_key());
+        }
+
+        let unique = positions
+            .iter()
+            .enumerate()
+            .filter(|(idx, pos)| {
+                !positions[..*idx]
+                    .iter()
+                    .any(|other| pos.eq_ignoring_counters(other))
+            })
+            .count();
+        assert_eq!(keys.len(), unique);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{limits::Limits, options::SearchOptions, search::search, tt::TranspositionTable};
+use chess::position::Position;
+use std::{sync::atomic::AtomicBool, time::Instant};
+
+/// The depth each bench position is searched to
+pub const BENCH_DEPTH: u32 = 5;
+
+/// A fixed set of positions used to compare the search between versions
+pub const BENCH_FENS: [&str; 8] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1B1PPP/R2QKB1R w KQ - 3 9",
+    "8/8/4k3/3p4/3P4/4K3/8/8 w - - 0 1",
+];
+
+/// Search each bench position to a fixed depth with a fresh transposition table
+/// Returns the total nodes searched and the time taken in milliseconds
+#[must_use]
+pub fn bench(depth: u32, options: &SearchOptions) -> (u64, u64) {
+    let start = Instant::now();
+    let limits = Limits {
+        depth: Some(depth),
+        ..Default::default()
+    };
+    let mut nodes = 0;
+
+    for fen in BENCH_FENS {
+        let mut tt = TranspositionTable::default();
+        let mut searched = 0;
+        let pos = Position::from_fen(fen);
+        let stop = AtomicBool::default();
+        let _ = search(&pos, &limits, options, &stop, &mut tt, &mut |report| {
+            searched = report.nodes;
+        });
+        nodes += searched;
+    }
+
+    (nodes, start.elapsed().as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deterministic() {
+        let options = SearchOptions::default();
+        let (nodes, _) = bench(3, &options);
+        assert!(nodes > 0);
+        assert_eq!(bench(3, &options).0, nodes);
+    }
+
+    #[test]
+    fn test_selectivity() {
+        // Each switch on its own cuts down the tree of a plain alpha-beta search
+        let (full, _) = bench(4, &SearchOptions::none());
+        for name in SearchOptions::NAMES {
+            let mut options = SearchOptions::none();
+            options.set(name, true);
+            assert!(bench(4, &options).0 < full, "{name}");
+        }
+        assert!(bench(4, &SearchOptions::default()).0 < full);
+    }
+}
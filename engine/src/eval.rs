@@ -0,0 +1,167 @@
+use chess::{Piece, Side, position::Position, square::Square};
+
+/// Material values in centipawns, indexed by piece type
+pub const PIECE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 0];
+
+// Piece-square tables from White's point of view, with a8 first and h1 last
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const KING_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+const TABLES: [[i32; 64]; 6] = [
+    PAWN_TABLE,
+    KNIGHT_TABLE,
+    BISHOP_TABLE,
+    ROOK_TABLE,
+    QUEEN_TABLE,
+    KING_TABLE,
+];
+
+/// Get the value of a piece on a square from White's point of view
+fn piece_score(piece: Piece, sq: Square) -> i32 {
+    let kind = piece as usize % 6;
+    if (piece as usize) < 6 {
+        PIECE_VALUES[kind] + TABLES[kind][(7 - sq.y as usize) * 8 + sq.x as usize]
+    } else {
+        -PIECE_VALUES[kind] - TABLES[kind][sq.y as usize * 8 + sq.x as usize]
+    }
+}
+
+/// Evaluate the position in centipawns from the side to move's point of view
+#[must_use]
+pub fn evaluate(pos: &Position) -> i32 {
+    let score = (0..64)
+        .map(Square::from_index)
+        .filter_map(|sq| {
+            pos.get_side_piece_on(sq)
+                .map(|piece| piece_score(piece, sq))
+        })
+        .sum::<i32>();
+
+    match pos.turn {
+        Side::White => score,
+        Side::Black => -score,
+    }
+}
+
+/// Get the material a side has besides pawns and the king
+#[must_use]
+pub fn non_pawn_material(pos: &Position, side: Side) -> i32 {
+    (0..64)
+        .map(Square::from_index)
+        .filter(|sq| pos.get_side_on(*sq) == Some(side))
+        .filter_map(|sq| pos.get_side_piece_on(sq))
+        .map(|piece| piece as usize % 6)
+        .filter(|kind| *kind != 0)
+        .map(|kind| PIECE_VALUES[kind])
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symmetry() {
+        assert_eq!(evaluate(&Position::from_fen("startpos")), 0);
+
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let pos = Position::from_fen(fen);
+            assert_eq!(evaluate(&pos), evaluate(&pos.mirrored()), "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_material() {
+        // White is a queen up, so the score is good for White and bad for Black
+        let white = Position::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1");
+        let black = Position::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1");
+        assert!(evaluate(&white) > 800);
+        assert_eq!(evaluate(&black), -evaluate(&white));
+    }
+
+    #[test]
+    fn test_non_pawn_material() {
+        let pos = Position::from_fen("startpos");
+        assert_eq!(
+            non_pawn_material(&pos, Side::White),
+            2 * 320 + 2 * 330 + 2 * 500 + 900
+        );
+
+        let pos = Position::from_fen("4k3/pp6/8/8/8/8/6P1/3RK3 w - - 0 1");
+        assert_eq!(non_pawn_material(&pos, Side::White), 500);
+        assert_eq!(non_pawn_material(&pos, Side::Black), 0);
+    }
+}
@@ -0,0 +1,90 @@
+use std::{
+    io::BufRead,
+    sync::{
+        atomic::AtomicBool,
+        mpsc::{Receiver, channel},
+    },
+    thread,
+};
+
+/// Flags shared between a protocol loop and its input thread
+#[derive(Debug, Default)]
+pub struct Signals {
+    /// Raised to stop the search
+    pub stop: AtomicBool,
+    /// Set while a search is running
+    pub searching: AtomicBool,
+    /// Raised along with stop when the search result should be thrown away
+    pub abort: AtomicBool,
+}
+
+/// Read lines on a separate thread, so commands can be acted on during a search
+/// Each line is first given to `intercept`, which handles anything that can't wait
+/// for the search to finish, and the line is only passed on if it returns true
+#[must_use]
+pub fn spawn_input<R, F>(input: R, mut intercept: F) -> Receiver<String>
+where
+    R: BufRead + Send + 'static,
+    F: FnMut(&str) -> bool + Send + 'static,
+{
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        for line in input.lines().map_while(Result::ok) {
+            if intercept(&line) && sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::{
+        io::{Cursor, Write},
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    /// Output shared between the main loop and the input thread
+    #[derive(Clone, Default)]
+    pub(crate) struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedOutput {
+        /// Wait until a line starting with the prefix has been written
+        pub(crate) fn wait_for(&self, prefix: &str) {
+            let start = Instant::now();
+            while !self.lines().iter().any(|line| line.starts_with(prefix)) {
+                assert!(start.elapsed() < Duration::from_secs(10), "No \"{prefix}\"");
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+
+        /// Get the lines written so far
+        pub(crate) fn lines(&self) -> Vec<String> {
+            let out = self.0.lock().unwrap();
+            String::from_utf8_lossy(&out)
+                .lines()
+                .map(str::to_string)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_intercept() {
+        let input = Cursor::new("one\ntwo\nthree\n");
+        let lines = spawn_input(input, |line| line != "two");
+        assert_eq!(lines.iter().collect::<Vec<_>>(), ["one", "three"]);
+    }
+}
@@ -0,0 +1,24 @@
+use chess::mv::Mv;
+
+/// The limits a search was started with, as given by the GUI
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Limits {
+    /// Time left on each side's clock in milliseconds
+    pub time: [Option<u64>; 2],
+    /// Increment per move for each side in milliseconds
+    pub inc: [Option<u64>; 2],
+    /// Moves until the next time control
+    pub movestogo: Option<u32>,
+    /// Exact time to spend on the move in milliseconds
+    pub movetime: Option<u64>,
+    /// Maximum depth to search to
+    pub depth: Option<u32>,
+    /// Maximum number of nodes to search
+    pub nodes: Option<u64>,
+    /// Search for a mate in this many moves
+    pub mate: Option<u32>,
+    /// Search until told to stop
+    pub infinite: bool,
+    /// Only search these moves at the root
+    pub searchmoves: Vec<Mv>,
+}
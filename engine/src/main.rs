@@ -1,3 +1,52 @@
+mod bench;
+mod eval;
+mod input;
+mod limits;
+mod movepick;
+mod options;
+mod search;
+mod timeman;
+mod tt;
+mod uci;
+mod xboard;
+
+use std::{
+    io::{BufRead, BufReader, stdin, stdout},
+    sync::Arc,
+};
+
 fn main() {
-    println!("Hello, world!");
+    // Running with "bench" searches a fixed set of positions and reports the node count
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        let (nodes, time) = bench::bench(bench::BENCH_DEPTH, &options::SearchOptions::default());
+        println!("{nodes} nodes {} nps", nodes * 1000 / time.max(1));
+        return;
+    }
+
+    let first = stdin()
+        .lock()
+        .lines()
+        .next()
+        .and_then(Result::ok)
+        .unwrap_or_default();
+
+    // The protocol is decided by the first command
+    // Input is read on another thread so a search can be interrupted
+    let signals = Arc::<input::Signals>::default();
+    if first.trim() == "xboard" {
+        let lines = input::spawn_input(BufReader::new(stdin()), {
+            let signals = Arc::clone(&signals);
+            move |line| {
+                xboard::intercept(line, &signals);
+                true
+            }
+        });
+        xboard::run(lines, stdout(), signals);
+    } else {
+        let lines = input::spawn_input(BufReader::new(stdin()), {
+            let signals = Arc::clone(&signals);
+            move |line| uci::intercept(line, &signals, &mut stdout())
+        });
+        uci::run(std::iter::once(first).chain(lines), stdout(), signals);
+    }
 }
@@ -0,0 +1,351 @@
+use crate::{eval::PIECE_VALUES, search::MAX_PLY};
+use chess::{
+    Piece, Side,
+    mv::{Mv, PromoPiece},
+    position::Position,
+    square::Square,
+};
+
+/// History scores are kept within this bound by the gravity update
+const MAX_HISTORY: i32 = 16384;
+
+// Ordering scores for quiet moves, highest first
+const KILLER: [i32; 2] = [2_000_000, 1_900_000];
+const COUNTER_MOVE: i32 = 1_800_000;
+
+/// The piece that moved and where it went, used to look up counter-moves
+pub type PrevMove = Option<(Piece, Square)>;
+
+/// Get the index of a square from 0 for a1 to 63 for h8
+fn sq_idx(sq: Square) -> usize {
+    sq.y as usize * 8 + sq.x as usize
+}
+
+/// Is the move neither a capture nor a promotion?
+#[must_use]
+pub fn is_quiet(pos: &Position, mv: &Mv) -> bool {
+    mv.promo.is_none() && capture_gain(pos, mv) == 0
+}
+
+/// Get the material a capture or promotion wins before any recapture
+#[must_use]
+pub fn capture_gain(pos: &Position, mv: &Mv) -> i32 {
+    let piece = pos.get_side_piece_on(mv.from);
+    let is_pawn = piece == Some(Piece::WP) || piece == Some(Piece::BP);
+
+    let captured = if is_pawn && pos.ep == Some(mv.to) {
+        PIECE_VALUES[0]
+    } else {
+        pos.get_side_piece_on(mv.to)
+            .map_or(0, |captured| PIECE_VALUES[captured as usize % 6])
+    };
+    let promoted = match mv.promo {
+        Some(PromoPiece::Queen) => PIECE_VALUES[4] - PIECE_VALUES[0],
+        Some(PromoPiece::Rook) => PIECE_VALUES[3] - PIECE_VALUES[0],
+        Some(PromoPiece::Bishop) => PIECE_VALUES[2] - PIECE_VALUES[0],
+        Some(PromoPiece::Knight) => PIECE_VALUES[1] - PIECE_VALUES[0],
+        None => 0,
+    };
+
+    captured + promoted
+}
+
+/// Most valuable victim, least valuable attacker ordering score
+#[must_use]
+pub fn mvv_lva(pos: &Position, mv: &Mv) -> i32 {
+    let attacker = pos
+        .get_side_piece_on(mv.from)
+        .expect("No piece on move origin square");
+    capture_gain(pos, mv) * 8 - (attacker as usize % 6) as i32
+}
+
+/// Move ordering information learned during a search
+pub struct Heuristics {
+    /// Two quiet moves per ply that recently caused a beta cutoff
+    killers: [[Option<Mv>; 2]; MAX_PLY],
+    /// The quiet move that refuted each previous move, indexed by piece and destination
+    counters: [[Option<Mv>; 64]; 12],
+    /// How often each quiet move caused a cutoff, indexed by side, origin and destination
+    history: Box<[[[i32; 64]; 64]; 2]>,
+}
+
+impl Default for Heuristics {
+    fn default() -> Self {
+        Self {
+            killers: [[None; 2]; MAX_PLY],
+            counters: [[None; 64]; 12],
+            history: Box::new([[[0; 64]; 64]; 2]),
+        }
+    }
+}
+
+impl Heuristics {
+    /// Get the history score of a quiet move
+    #[must_use]
+    pub fn history(&self, side: Side, mv: &Mv) -> i32 {
+        self.history[side as usize][sq_idx(mv.from)][sq_idx(mv.to)]
+    }
+
+    /// Get the counter-move to the previous move
+    #[must_use]
+    pub fn counter(&self, prev: PrevMove) -> Option<Mv> {
+        prev.and_then(|(piece, to)| self.counters[piece as usize][sq_idx(to)])
+    }
+
+    /// Nudge a history score towards the bound, slowing down as it gets closer
+    fn update_history(&mut self, side: Side, mv: &Mv, bonus: i32) {
+        let entry = &mut self.history[side as usize][sq_idx(mv.from)][sq_idx(mv.to)];
+        *entry += bonus - *entry * bonus.abs() / MAX_HISTORY;
+    }
+
+    /// Record a quiet move causing a beta cutoff
+    /// The quiet moves tried before it are penalised
+    pub fn update_quiet(
+        &mut self,
+        side: Side,
+        ply: usize,
+        prev: PrevMove,
+        mv: Mv,
+        tried: &[Mv],
+        depth: i32,
+    ) {
+        if self.killers[ply][0] != Some(mv) {
+            self.killers[ply][1] = self.killers[ply][0];
+            self.killers[ply][0] = Some(mv);
+        }
+
+        if let Some((piece, to)) = prev {
+            self.counters[piece as usize][sq_idx(to)] = Some(mv);
+        }
+
+        let bonus = (depth * depth).min(1200);
+        self.update_history(side, &mv, bonus);
+        for other in tried.iter().filter(|other| **other != mv) {
+            self.update_history(side, other, -bonus);
+        }
+    }
+}
+
+/// The groups of moves handed out by `MovePicker`, in order
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+enum Stage {
+    TtMove,
+    GoodCaptures,
+    Quiets,
+    BadCaptures,
+}
+
+/// Hands out moves from best to worst in stages
+/// The order is the hash move, good captures by MVV-LVA, killers, the counter-move,
+/// quiet moves by history, then captures that lose material
+/// Each stage is only scored once the one before it runs out, so a cutoff from an early
+/// move saves the work of ordering the rest
+pub struct MovePicker {
+    pos: Position,
+    stage: Stage,
+    tt_move: Option<Mv>,
+    ply: usize,
+    prev: PrevMove,
+    /// The moves not handed out or scored yet
+    unscored: Vec<Mv>,
+    /// The moves of the current stage with their ordering scores
+    scored: Vec<(Mv, i32)>,
+    /// Captures that failed the exchange check, tried last
+    bad_captures: Vec<(Mv, i32)>,
+}
+
+impl MovePicker {
+    /// Start picking from the pseudolegal moves of a position
+    #[must_use]
+    pub fn new(pos: &Position, tt_move: Option<Mv>, ply: usize, prev: PrevMove) -> Self {
+        let unscored = pos.pseudolegal_moves();
+        Self {
+            pos: *pos,
+            stage: Stage::TtMove,
+            tt_move: tt_move.filter(|mv| unscored.contains(mv)),
+            ply,
+            prev,
+            unscored,
+            scored: vec![],
+            bad_captures: vec![],
+        }
+    }
+
+    /// Take the next move, or None once every move has been handed out
+    /// The heuristics are only looked at when the quiet moves are reached
+    pub fn next_move(&mut self, heuristics: &Heuristics) -> Option<Mv> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.unscored.retain(|mv| Some(*mv) != self.tt_move);
+                    self.scored = self
+                        .unscored
+                        .iter()
+                        .filter(|mv| !is_quiet(&self.pos, mv))
+                        .map(|mv| (*mv, mvv_lva(&self.pos, mv)))
+                        .collect();
+                    self.stage = Stage::GoodCaptures;
+                    if self.tt_move.is_some() {
+                        return self.tt_move;
+                    }
+                }
+                Stage::GoodCaptures => {
+                    // Only captures that could be tried get an exchange evaluation
+                    while let Some((mv, score)) = take_best(&mut self.scored) {
+                        if self.pos.see_ge(&mv, 0) {
+                            return Some(mv);
+                        }
+                        self.bad_captures.push((mv, score));
+                    }
+
+                    let killers = heuristics.killers[self.ply];
+                    let counter = heuristics.counter(self.prev);
+                    self.scored = self
+                        .unscored
+                        .iter()
+                        .filter(|mv| is_quiet(&self.pos, mv))
+                        .map(|mv| {
+                            let score = if Some(*mv) == killers[0] {
+                                KILLER[0]
+                            } else if Some(*mv) == killers[1] {
+                                KILLER[1]
+                            } else if Some(*mv) == counter {
+                                COUNTER_MOVE
+                            } else {
+                                heuristics.history(self.pos.turn, mv)
+                            };
+                            (*mv, score)
+                        })
+                        .collect();
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => {
+                    if let Some((mv, _)) = take_best(&mut self.scored) {
+                        return Some(mv);
+                    }
+                    self.stage = Stage::BadCaptures;
+                }
+                Stage::BadCaptures => return take_best(&mut self.bad_captures).map(|(mv, _)| mv),
+            }
+        }
+    }
+}
+
+/// Remove and return the move with the highest score, the earliest on a tie
+fn take_best(moves: &mut Vec<(Mv, i32)>) -> Option<(Mv, i32)> {
+    let (idx, _) = moves
+        .iter()
+        .enumerate()
+        .max_by_key(|(idx, (_, score))| (*score, std::cmp::Reverse(*idx)))?;
+    Some(moves.remove(idx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mvv_lva() {
+        let pos = Position::from_fen("4k3/8/8/2q1r3/1P1P4/8/8/4K3 w - - 0 1");
+        let pxq = Mv::from_string("b4c5");
+        let pxr = Mv::from_string("d4e5");
+        let pxq2 = Mv::from_string("d4c5");
+        assert!(mvv_lva(&pos, &pxq) > mvv_lva(&pos, &pxr));
+        assert_eq!(mvv_lva(&pos, &pxq), mvv_lva(&pos, &pxq2));
+
+        // Capturing with a pawn is preferred over capturing with a queen
+        let pos = Position::from_fen("4k3/8/8/3r4/2P5/8/8/3QK3 w - - 0 1");
+        assert!(mvv_lva(&pos, &Mv::from_string("c4d5")) > mvv_lva(&pos, &Mv::from_string("d1d5")));
+
+        // En passant and promotion
+        let pos = Position::from_fen("4k3/P7/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        assert_eq!(
+            capture_gain(&pos, &Mv::from_string("e5d6")),
+            PIECE_VALUES[0]
+        );
+        assert_eq!(
+            capture_gain(&pos, &Mv::from_string("a7a8q")),
+            PIECE_VALUES[4] - PIECE_VALUES[0]
+        );
+    }
+
+    #[test]
+    fn test_order() {
+        let pos = Position::from_fen("4k3/8/2n5/3p4/4P3/1q4p1/8/R3K2N w - - 0 1");
+        let mut heuristics = Heuristics::default();
+        heuristics.update_quiet(Side::White, 3, None, Mv::from_string("a1a7"), &[], 4);
+        heuristics.update_quiet(Side::White, 3, None, Mv::from_string("a1a8"), &[], 4);
+        heuristics.update_quiet(Side::White, 2, None, Mv::from_string("a1a6"), &[], 4);
+        heuristics.update_quiet(
+            Side::White,
+            0,
+            Some((Piece::BQ, Square::from_string("b3"))),
+            Mv::from_string("a1a5"),
+            &[],
+            4,
+        );
+        heuristics.update_quiet(Side::White, 0, None, Mv::from_string("e1d1"), &[], 8);
+
+        let mut picker = MovePicker::new(
+            &pos,
+            Some(Mv::from_string("a1a2")),
+            3,
+            Some((Piece::BQ, Square::from_string("b3"))),
+        );
+        let moves = std::iter::from_fn(|| picker.next_move(&heuristics)).collect::<Vec<_>>();
+        assert_eq!(moves.len(), pos.pseudolegal_moves().len());
+
+        let expected = [
+            // Hash move
+            "a1a2", // Good captures
+            "e4d5", // Killers, most recent first
+            "a1a8", "a1a7", // Counter-move
+            "a1a5", // Quiets by history
+            "e1d1",
+        ];
+        for (idx, movestr) in expected.iter().enumerate() {
+            assert_eq!(moves[idx].to_string(), *movestr, "{moves:?}");
+        }
+
+        // The losing capture of the defended pawn is last
+        assert_eq!(moves.last().unwrap().to_string(), "h1g3");
+    }
+
+    #[test]
+    fn test_tt_move() {
+        let pos = Position::from_fen("startpos");
+        let heuristics = Heuristics::default();
+
+        // The hash move comes first and isn't handed out again
+        let mut picker = MovePicker::new(&pos, Some(Mv::from_string("g1f3")), 0, None);
+        let moves = std::iter::from_fn(|| picker.next_move(&heuristics)).collect::<Vec<_>>();
+        assert_eq!(moves[0], Mv::from_string("g1f3"));
+        assert_eq!(moves.len(), 20);
+        assert_eq!(
+            moves.iter().filter(|mv| mv.to_string() == "g1f3").count(),
+            1
+        );
+
+        // A hash move that can't be played in the position is ignored
+        let mut picker = MovePicker::new(&pos, Some(Mv::from_string("e2e5")), 0, None);
+        let moves = std::iter::from_fn(|| picker.next_move(&heuristics)).collect::<Vec<_>>();
+        assert_eq!(moves.len(), 20);
+        assert!(!moves.contains(&Mv::from_string("e2e5")));
+    }
+
+    #[test]
+    fn test_history_gravity() {
+        let mut heuristics = Heuristics::default();
+        let mv = Mv::from_string("e2e4");
+        let other = Mv::from_string("d2d4");
+
+        for _ in 0..1000 {
+            heuristics.update_quiet(Side::White, 0, None, mv, &[other], 30);
+        }
+        let score = heuristics.history(Side::White, &mv);
+        assert!(score > 0 && score <= MAX_HISTORY);
+        assert!(heuristics.history(Side::White, &other) >= -MAX_HISTORY);
+        assert!(heuristics.history(Side::White, &other) < 0);
+        assert_eq!(heuristics.history(Side::Black, &mv), 0);
+    }
+}
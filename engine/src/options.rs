@@ -0,0 +1,99 @@
+use crate::timeman::DEFAULT_MOVE_OVERHEAD;
+
+/// Settings for the search
+/// The switches turn the selective parts of the search on and off, so each can be tested on its own
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct SearchOptions {
+    pub null_move: bool,
+    pub lmr: bool,
+    pub reverse_futility: bool,
+    pub futility: bool,
+    pub late_move_pruning: bool,
+    pub razoring: bool,
+    /// Time kept back each move for communication lag, in milliseconds
+    pub move_overhead: u64,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            null_move: true,
+            lmr: true,
+            reverse_futility: true,
+            futility: true,
+            late_move_pruning: true,
+            razoring: true,
+            move_overhead: DEFAULT_MOVE_OVERHEAD,
+        }
+    }
+}
+
+impl SearchOptions {
+    /// The names of the switches, as shown to the GUI
+    pub const NAMES: [&str; 6] = [
+        "NullMove",
+        "LMR",
+        "ReverseFutility",
+        "Futility",
+        "LateMovePruning",
+        "Razoring",
+    ];
+
+    /// Get a switch by its name, ignoring case
+    fn switch_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name.to_lowercase().as_str() {
+            "nullmove" => Some(&mut self.null_move),
+            "lmr" => Some(&mut self.lmr),
+            "reversefutility" => Some(&mut self.reverse_futility),
+            "futility" => Some(&mut self.futility),
+            "latemovepruning" => Some(&mut self.late_move_pruning),
+            "razoring" => Some(&mut self.razoring),
+            _ => None,
+        }
+    }
+
+    /// Turn a switch on or off by its name
+    /// Returns false if there's no switch with that name
+    pub fn set(&mut self, name: &str, value: bool) -> bool {
+        match self.switch_mut(name) {
+            Some(switch) => {
+                *switch = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get every switch turned off, leaving a plain alpha-beta search
+    #[cfg(test)]
+    #[must_use]
+    pub fn none() -> Self {
+        Self {
+            null_move: false,
+            lmr: false,
+            reverse_futility: false,
+            futility: false,
+            late_move_pruning: false,
+            razoring: false,
+            ..Self::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set() {
+        let mut options = SearchOptions::default();
+        for name in SearchOptions::NAMES {
+            assert!(options.set(name, false), "{name}");
+        }
+        assert_eq!(options, SearchOptions::none());
+
+        assert!(options.set("nullmove", true));
+        assert!(options.null_move);
+        assert!(!options.set("Nonsense", true));
+    }
+}
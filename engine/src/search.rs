@@ -0,0 +1,726 @@
+use crate::{
+    eval::{evaluate, non_pawn_material},
+    limits::Limits,
+    movepick::{Heuristics, MovePicker, PrevMove, is_quiet},
+    options::SearchOptions,
+    timeman::{TimeManager, time_scale},
+    tt::{Bound, TranspositionTable},
+};
+use chess::{mv::Mv, position::Position};
+use pruning::{
+    FUTILITY_MAX_DEPTH, LMP_MAX_DEPTH, NMP_MIN_DEPTH, NMP_VERIFY_MATERIAL, RAZOR_MARGIN,
+    RFP_MARGIN, RFP_MAX_DEPTH, futility_margin, lmp_threshold, lmr_reduction, null_move_reduction,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+mod pruning;
+mod qsearch;
+
+/// A score bigger than any real score
+pub const INF: i32 = 32_000;
+/// The score for checkmating on the current move, reduced by one per ply
+pub const MATE: i32 = 31_000;
+/// The deepest the search can go
+pub const MAX_PLY: usize = 128;
+
+/// Is the score a forced mate for either side?
+#[must_use]
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE - MAX_PLY as i32
+}
+
+/// Get the number of moves to mate from a mate score
+/// The number is negative if the side to move is getting mated
+#[must_use]
+pub fn mate_in(score: i32) -> i32 {
+    if score > 0 {
+        (MATE - score + 1) / 2
+    } else {
+        -(MATE + score + 1) / 2
+    }
+}
+
+/// The clock is read once per this many nodes
+const TIME_CHECK_INTERVAL: u64 = 1024;
+
+/// The half-width of the first aspiration window around the previous score
+const ASPIRATION_WINDOW: i32 = 25;
+/// Aspiration windows are only used from this depth, when the previous score is reliable
+const ASPIRATION_MIN_DEPTH: usize = 4;
+
+/// Progress reported by the search after each iteration, and when an aspiration window fails
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Report {
+    pub depth: u32,
+    /// Score in centipawns from the side to move's point of view, or a mate score
+    pub score: i32,
+    /// Whether the score is exact, or only a bound because the aspiration window failed
+    pub bound: Bound,
+    pub nodes: u64,
+    /// Time spent in milliseconds
+    pub time: u64,
+    /// How full the transposition table is in permille
+    pub hashfull: u32,
+    pub pv: Vec<Mv>,
+}
+
+/// The state of a single search
+pub struct Searcher<'a> {
+    limits: &'a Limits,
+    options: &'a SearchOptions,
+    /// Raised by another thread to stop the search
+    stop: &'a AtomicBool,
+    tt: &'a mut TranspositionTable,
+    tm: TimeManager,
+    stopped: bool,
+    pub nodes: u64,
+    /// Triangular principal variation table, row `ply` holds the PV from that ply
+    pv: Vec<[Option<Mv>; MAX_PLY]>,
+    pv_len: [usize; MAX_PLY],
+    heuristics: Heuristics,
+    /// The piece moved and its destination at each ply, for counter-moves
+    /// None after a null move
+    stack: [PrevMove; MAX_PLY],
+    /// Whether a null move cutoff is being verified, which turns off null moves below it
+    verifying: bool,
+}
+
+impl<'a> Searcher<'a> {
+    /// Create a searcher for the limits given, starting the clock now
+    pub fn new(
+        limits: &'a Limits,
+        options: &'a SearchOptions,
+        stop: &'a AtomicBool,
+        pos: &Position,
+        tt: &'a mut TranspositionTable,
+    ) -> Self {
+        tt.new_search();
+
+        Self {
+            limits,
+            options,
+            stop,
+            tt,
+            tm: TimeManager::new(limits, pos.turn, options.move_overhead),
+            stopped: false,
+            nodes: 0,
+            pv: vec![[None; MAX_PLY]; MAX_PLY],
+            pv_len: [0; MAX_PLY],
+            heuristics: Heuristics::default(),
+            stack: [None; MAX_PLY],
+            verifying: false,
+        }
+    }
+
+    /// Check whether the search should stop
+    /// The node limit is checked at every node so it's deterministic,
+    /// but the clock and the stop flag are only read every few nodes
+    fn check_stop(&mut self) {
+        if self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes)
+            || self.nodes.is_multiple_of(TIME_CHECK_INTERVAL)
+                && (self.stop.load(Ordering::Relaxed) || self.tm.out_of_time())
+        {
+            self.stopped = true;
+        }
+    }
+
+    /// Get the principal variation from the root
+    fn root_pv(&self) -> Vec<Mv> {
+        self.pv[0][..self.pv_len[0]]
+            .iter()
+            .map(|mv| mv.expect("Missing PV move"))
+            .collect()
+    }
+
+    /// Put a move at the head of the PV for this ply, followed by the PV of the next ply
+    fn update_pv(&mut self, ply: usize, mv: Mv) {
+        self.pv[ply][ply] = Some(mv);
+        for idx in ply + 1..self.pv_len[ply + 1] {
+            self.pv[ply][idx] = self.pv[ply + 1][idx];
+        }
+        self.pv_len[ply] = self.pv_len[ply + 1].max(ply + 1);
+    }
+
+    /// Negamax alpha-beta search with principal variation search
+    /// Returns the score from the side to move's point of view
+    fn negamax(
+        &mut self,
+        pos: &Position,
+        depth: i32,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        if depth <= 0 {
+            return self.qsearch(pos, ply, alpha, beta);
+        }
+
+        self.pv_len[ply] = ply;
+        self.nodes += 1;
+        self.check_stop();
+        if self.stopped {
+            return 0;
+        }
+
+        if ply >= MAX_PLY - 1 {
+            return evaluate(pos);
+        }
+
+        // Fifty move rule
+        if ply > 0 && pos.halfmoves >= 100 {
+            return 0;
+        }
+
+        // Only nodes with an open window can become part of the PV
+        let pv_node = beta - alpha > 1;
+
+        // Transposition table cutoffs, except in PV nodes so the PV isn't cut short
+        let key = pos.hash_key();
+        let tt_entry = self.tt.probe(key);
+        if !pv_node
+            && let Some(entry) = tt_entry
+            && entry.depth >= depth
+        {
+            let score = entry.score(ply);
+            match entry.bound {
+                Bound::Exact => return score,
+                Bound::Lower if score >= beta => return score,
+                Bound::Upper if score <= alpha => return score,
+                _ => {}
+            }
+        }
+        let eval = tt_entry.map_or_else(|| evaluate(pos), |entry| entry.eval);
+        let in_check = pos.in_check();
+
+        if !pv_node && !in_check {
+            // Razoring, drop into the quiescence search if the eval is far below alpha
+            if self.options.razoring
+                && depth < RAZOR_MARGIN.len() as i32
+                && eval + RAZOR_MARGIN[depth as usize] < alpha
+            {
+                let score = self.qsearch(pos, ply, alpha, beta);
+                if score < alpha {
+                    return score;
+                }
+            }
+
+            // Reverse futility pruning, assume the eval holds up if it's far above beta
+            if self.options.reverse_futility
+                && depth <= RFP_MAX_DEPTH
+                && !is_mate_score(beta)
+                && eval - RFP_MARGIN * depth >= beta
+            {
+                return eval;
+            }
+
+            // Null move pruning, if passing still beats beta then a real move probably will too
+            // Not tried twice in a row, or without pieces where zugzwang is likely
+            if self.options.null_move
+                && !self.verifying
+                && depth >= NMP_MIN_DEPTH
+                && eval >= beta
+                && self.stack[ply - 1].is_some()
+                && non_pawn_material(pos, pos.turn) > 0
+            {
+                let reduced = depth - 1 - null_move_reduction(depth, eval, beta);
+                let mut npos = *pos;
+                if npos.make_null_move().is_some() {
+                    self.stack[ply] = None;
+                    let score = -self.negamax(&npos, reduced, ply + 1, -beta, -beta + 1);
+                    if self.stopped {
+                        return 0;
+                    }
+
+                    if score >= beta {
+                        // Don't trust mate scores from a position with an illegal move in it
+                        let score = if is_mate_score(score) { beta } else { score };
+                        if non_pawn_material(pos, pos.turn) > NMP_VERIFY_MATERIAL {
+                            return score;
+                        }
+
+                        // Verify the cutoff with a reduced search without null moves
+                        self.verifying = true;
+                        let verified = self.negamax(pos, reduced, ply, beta - 1, beta);
+                        self.verifying = false;
+                        if self.stopped {
+                            return 0;
+                        }
+                        if verified >= beta {
+                            return score;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Futility pruning, quiet moves can't bring the eval up to alpha
+        let futile = self.options.futility
+            && ply > 0
+            && !in_check
+            && depth <= FUTILITY_MAX_DEPTH
+            && eval + futility_margin(depth) <= alpha;
+
+        let prev = if ply > 0 { self.stack[ply - 1] } else { None };
+        let mut moves = MovePicker::new(pos, tt_entry.and_then(|entry| entry.mv), ply, prev);
+
+        let original_alpha = alpha;
+        let mut best = -INF;
+        let mut best_move = None;
+        let mut legal = 0;
+        let mut quiets_tried = vec![];
+
+        while let Some(mv) = moves.next_move(&self.heuristics) {
+            if ply == 0
+                && !self.limits.searchmoves.is_empty()
+                && !self.limits.searchmoves.contains(&mv)
+            {
+                continue;
+            }
+
+            // Skip quiet moves once we have a move, unless we're getting mated
+            let quiet = is_quiet(pos, &mv);
+            if ply > 0 && quiet && !in_check && legal > 0 && best > -MATE + MAX_PLY as i32 {
+                if futile && !pos.gives_check(&mv) {
+                    continue;
+                }
+
+                // Late move pruning, good moves are likely to have been ordered early
+                if self.options.late_move_pruning
+                    && depth <= LMP_MAX_DEPTH
+                    && legal >= lmp_threshold(depth)
+                {
+                    continue;
+                }
+            }
+
+            let mut npos = *pos;
+            if !npos.makemove(&mv) {
+                continue;
+            }
+            legal += 1;
+
+            // Late move reductions, search later quiet moves less deeply
+            // PV nodes are reduced less
+            let reduction = if self.options.lmr
+                && depth >= 3
+                && legal > 3
+                && quiet
+                && !in_check
+                && !npos.in_check()
+            {
+                (lmr_reduction(depth, legal) - i32::from(pv_node)).clamp(0, depth - 2)
+            } else {
+                0
+            };
+
+            self.stack[ply] = pos.get_side_piece_on(mv.from).map(|piece| (piece, mv.to));
+            let mut score;
+            if legal == 1 {
+                score = -self.negamax(&npos, depth - 1, ply + 1, -beta, -alpha);
+            } else {
+                // Try to prove the move is worse than the best so far with a zero window
+                score = -self.negamax(&npos, depth - 1 - reduction, ply + 1, -alpha - 1, -alpha);
+
+                // Search again at full depth if the reduced search beats alpha
+                if reduction > 0 && score > alpha && !self.stopped {
+                    score = -self.negamax(&npos, depth - 1, ply + 1, -alpha - 1, -alpha);
+                }
+
+                // Search again with the full window to get an exact score for the PV
+                if score > alpha && score < beta && !self.stopped {
+                    score = -self.negamax(&npos, depth - 1, ply + 1, -beta, -alpha);
+                }
+            }
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                best_move = Some(mv);
+                if score > alpha {
+                    alpha = score;
+                    self.update_pv(ply, mv);
+                    if score >= beta {
+                        if quiet {
+                            self.heuristics.update_quiet(
+                                pos.turn,
+                                ply,
+                                prev,
+                                mv,
+                                &quiets_tried,
+                                depth,
+                            );
+                        }
+                        break;
+                    }
+                }
+            }
+
+            if quiet {
+                quiets_tried.push(mv);
+            }
+        }
+
+        // Checkmate or stalemate
+        if legal == 0 {
+            return if in_check { -MATE + ply as i32 } else { 0 };
+        }
+
+        let bound = if best >= beta {
+            Bound::Lower
+        } else if best > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.tt.store(key, best_move, depth, bound, best, eval, ply);
+
+        best
+    }
+
+    /// Report the progress of the search
+    fn report(
+        &self,
+        report: &mut dyn FnMut(&Report),
+        depth: usize,
+        score: i32,
+        bound: Bound,
+        pv: Vec<Mv>,
+    ) {
+        report(&Report {
+            depth: depth as u32,
+            score,
+            bound,
+            nodes: self.nodes,
+            time: self.tm.elapsed().as_millis() as u64,
+            hashfull: self.tt.hashfull(),
+            pv,
+        });
+    }
+
+    /// Search with iterative deepening until a limit is reached
+    /// Each iteration after the first few searches a window around the previous score,
+    /// widening it whenever the score falls outside
+    /// With a clock, more time is spent while the best move changes or the score drops
+    /// Returns None if there are no legal moves
+    pub fn iterate(&mut self, pos: &Position, report: &mut dyn FnMut(&Report)) -> Option<Mv> {
+        let root_moves = pos
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| self.limits.searchmoves.is_empty() || self.limits.searchmoves.contains(mv))
+            .collect::<Vec<_>>();
+
+        // Fall back on any move in case the first iteration doesn't finish
+        let mut bestmove = *root_moves.first()?;
+        let max_depth = self
+            .limits
+            .depth
+            .map_or(MAX_PLY - 1, |depth| (depth as usize).clamp(1, MAX_PLY - 1));
+
+        let mut score = 0;
+        let mut pv = vec![];
+        let mut best_move_changes = 0.0;
+
+        'deepening: for depth in 1..=max_depth {
+            let iteration_start = self.tm.elapsed();
+            let previous_score = score;
+            let mut delta = ASPIRATION_WINDOW;
+            let (mut alpha, mut beta) = if depth >= ASPIRATION_MIN_DEPTH {
+                ((score - delta).max(-INF), (score + delta).min(INF))
+            } else {
+                (-INF, INF)
+            };
+
+            loop {
+                let result = self.negamax(pos, depth as i32, 0, alpha, beta);
+                if self.stopped {
+                    break 'deepening;
+                }
+
+                if result <= alpha {
+                    // Failing low leaves no PV, so show the last one
+                    self.report(report, depth, result, Bound::Upper, pv.clone());
+                    beta = (alpha + beta) / 2;
+                    alpha = (result - delta).max(-INF);
+                } else if result >= beta {
+                    self.report(report, depth, result, Bound::Lower, self.root_pv());
+                    beta = (result + delta).min(INF);
+                } else {
+                    score = result;
+                    break;
+                }
+                delta *= 2;
+            }
+
+            // Recent changes of mind count for more than old ones
+            best_move_changes /= 2.0;
+            if depth > 1 && self.pv[0][0] != Some(bestmove) {
+                best_move_changes += 1.0;
+            }
+
+            pv = self.root_pv();
+            bestmove = pv[0];
+            self.report(report, depth, score, Bound::Exact, pv.clone());
+
+            // Stop once there's a mate within the moves asked for
+            if self.limits.mate.is_some_and(|moves| {
+                is_mate_score(score) && score > 0 && mate_in(score) <= moves as i32
+            }) {
+                break;
+            }
+
+            let last_iteration = self.tm.elapsed() - iteration_start;
+            let score_drop = if depth > 1 { previous_score - score } else { 0 };
+            let scale = time_scale(best_move_changes, score_drop);
+            if self.tm.should_stop(scale, last_iteration) {
+                break;
+            }
+        }
+
+        Some(bestmove)
+    }
+}
+
+/// Search the position within the limits given, reporting progress after each iteration
+/// The search also stops soon after the stop flag is raised
+/// Returns None if there are no legal moves
+pub fn search(
+    pos: &Position,
+    limits: &Limits,
+    options: &SearchOptions,
+    stop: &AtomicBool,
+    tt: &mut TranspositionTable,
+    report: &mut dyn FnMut(&Report),
+) -> Option<Mv> {
+    Searcher::new(limits, options, stop, pos, tt).iterate(pos, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Search to a fixed depth and collect the reports
+    fn search_depth(fen: &str, depth: u32) -> (Option<Mv>, Vec<Report>) {
+        search_depth_with(fen, depth, &SearchOptions::default())
+    }
+
+    /// Search to a fixed depth with the options given and collect the reports
+    fn search_depth_with(
+        fen: &str,
+        depth: u32,
+        options: &SearchOptions,
+    ) -> (Option<Mv>, Vec<Report>) {
+        let limits = Limits {
+            depth: Some(depth),
+            ..Default::default()
+        };
+        search_limits(fen, &limits, options)
+    }
+
+    /// Search within the limits given and collect the reports
+    fn search_limits(
+        fen: &str,
+        limits: &Limits,
+        options: &SearchOptions,
+    ) -> (Option<Mv>, Vec<Report>) {
+        let mut reports = vec![];
+        let bestmove = search(
+            &Position::from_fen(fen),
+            limits,
+            options,
+            &AtomicBool::default(),
+            &mut TranspositionTable::new(1),
+            &mut |report| {
+                reports.push(report.clone());
+            },
+        );
+        (bestmove, reports)
+    }
+
+    #[test]
+    fn test_mate_in() {
+        assert_eq!(mate_in(MATE - 1), 1);
+        assert_eq!(mate_in(MATE - 3), 2);
+        assert_eq!(mate_in(-MATE + 2), -1);
+        assert_eq!(mate_in(-MATE + 4), -2);
+        assert!(is_mate_score(MATE - 10));
+        assert!(is_mate_score(-MATE + 10));
+        assert!(!is_mate_score(2000));
+    }
+
+    #[test]
+    fn test_mate() {
+        // Mate in one
+        let (bestmove, reports) = search_depth("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+        assert_eq!(bestmove, Some(Mv::from_string("a1a8")));
+        assert_eq!(reports.last().unwrap().score, MATE - 1);
+
+        // Mate in two, where pruning needs a little more depth to see past the quiet first move
+        let fen = "k7/8/2K5/8/8/8/8/7R w - - 0 1";
+        let (_, reports) = search_depth_with(fen, 4, &SearchOptions::none());
+        assert_eq!(mate_in(reports.last().unwrap().score), 2);
+        let (bestmove, reports) = search_depth(fen, 6);
+        assert_eq!(mate_in(reports.last().unwrap().score), 2);
+        assert!(bestmove.is_some());
+
+        // Getting mated in one
+        let (_, reports) = search_depth("k7/2K5/8/8/8/8/8/1R6 b - - 0 1", 3);
+        assert_eq!(reports.last().unwrap().score, -MATE + 2);
+    }
+
+    #[test]
+    fn test_no_moves() {
+        // Checkmated
+        let (bestmove, reports) = search_depth("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", 3);
+        assert_eq!(bestmove, None);
+        assert!(reports.is_empty());
+
+        // Stalemated
+        let (bestmove, _) = search_depth("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 3);
+        assert_eq!(bestmove, None);
+    }
+
+    #[test]
+    fn test_captures() {
+        let (bestmove, _) = search_depth("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", 2);
+        assert_eq!(bestmove, Some(Mv::from_string("d1d5")));
+    }
+
+    #[test]
+    fn test_reports() {
+        let (bestmove, reports) = search_depth("startpos", 3);
+        assert_eq!(reports.len(), 3);
+
+        for (idx, report) in reports.iter().enumerate() {
+            assert_eq!(report.depth, idx as u32 + 1);
+            assert!(!report.pv.is_empty());
+
+            // The PV is a sequence of legal moves
+            let mut pos = Position::from_fen("startpos");
+            for mv in &report.pv {
+                assert!(pos.legal_moves().contains(mv));
+                let success = pos.makemove(mv);
+                assert!(success);
+            }
+        }
+        assert_eq!(bestmove, Some(reports[2].pv[0]));
+        assert!(reports[0].nodes < reports[2].nodes);
+    }
+
+    #[test]
+    fn test_aspiration() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let (bestmove, reports) = search_depth(fen, 6);
+
+        // Failed windows are reported as bounds, then every depth finishes with an exact score
+        assert!(reports.iter().any(|report| report.bound != Bound::Exact));
+        let exact = reports
+            .iter()
+            .filter(|report| report.bound == Bound::Exact)
+            .map(|report| report.depth)
+            .collect::<Vec<_>>();
+        assert_eq!(exact, (1..=6).collect::<Vec<_>>());
+        assert_eq!(reports.last().unwrap().bound, Bound::Exact);
+        assert_eq!(bestmove, Some(reports.last().unwrap().pv[0]));
+
+        for pair in reports.windows(2) {
+            assert!(pair[0].depth <= pair[1].depth);
+            assert!(pair[0].nodes <= pair[1].nodes);
+        }
+    }
+
+    #[test]
+    fn test_tt_root_entry() {
+        let pos = Position::from_fen(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        );
+        let limits = Limits {
+            depth: Some(2),
+            ..Default::default()
+        };
+        let mut tt = TranspositionTable::new(1);
+        let mut score = 0;
+        let bestmove = search(
+            &pos,
+            &limits,
+            &SearchOptions::default(),
+            &AtomicBool::default(),
+            &mut tt,
+            &mut |report| score = report.score,
+        );
+
+        // The root result is left in the table for the next search
+        let entry = tt.probe(pos.hash_key()).unwrap();
+        assert_eq!(entry.mv, bestmove);
+        assert_eq!(entry.depth, 2);
+        assert_eq!(entry.bound, Bound::Exact);
+        assert_eq!(entry.score(0), score);
+    }
+
+    #[test]
+    fn test_searchmoves() {
+        let limits = Limits {
+            depth: Some(2),
+            searchmoves: vec![Mv::from_string("a2a3")],
+            ..Default::default()
+        };
+        let (bestmove, _) = search_limits("startpos", &limits, &SearchOptions::default());
+        assert_eq!(bestmove, Some(Mv::from_string("a2a3")));
+    }
+
+    #[test]
+    fn test_node_limit() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let limits = Limits {
+            nodes: Some(5000),
+            ..Default::default()
+        };
+        let (bestmove, reports) = search_limits(fen, &limits, &SearchOptions::default());
+        assert!(reports.iter().all(|report| report.nodes <= 5000));
+
+        // The same limit gives the same search every time
+        let (again, again_reports) = search_limits(fen, &limits, &SearchOptions::default());
+        assert_eq!(bestmove, again);
+        let summary = |reports: &[Report]| {
+            reports
+                .iter()
+                .map(|report| (report.depth, report.score, report.nodes, report.pv.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(summary(&reports), summary(&again_reports));
+    }
+
+    #[test]
+    fn test_mate_limit() {
+        // The search stops as soon as the mate is found, without a depth limit
+        let limits = Limits {
+            mate: Some(2),
+            ..Default::default()
+        };
+        let (bestmove, reports) = search_limits(
+            "k7/8/2K5/8/8/8/8/7R w - - 0 1",
+            &limits,
+            &SearchOptions::default(),
+        );
+        let last = reports.last().unwrap();
+        assert_eq!(mate_in(last.score), 2);
+        assert_eq!(bestmove, Some(last.pv[0]));
+        assert!(last.depth < 10);
+    }
+
+    #[test]
+    fn test_stop_flag() {
+        // A search without limits returns straight away once stopped
+        let stop = AtomicBool::new(true);
+        let bestmove = search(
+            &Position::from_fen("startpos"),
+            &Limits::default(),
+            &SearchOptions::default(),
+            &stop,
+            &mut TranspositionTable::new(1),
+            &mut |_| {},
+        );
+        assert!(bestmove.is_some());
+    }
+}
@@ -0,0 +1,74 @@
+use crate::eval::PIECE_VALUES;
+use std::sync::LazyLock;
+
+/// Razoring drops into the quiescence search if the static eval is this far below alpha, by depth
+pub(super) const RAZOR_MARGIN: [i32; 3] = [0, 300, 550];
+
+/// Reverse futility pruning trusts the static eval if it beats beta by this much per ply
+pub(super) const RFP_MARGIN: i32 = 80;
+pub(super) const RFP_MAX_DEPTH: i32 = 6;
+
+/// Futility pruning skips quiet moves if the static eval plus a margin can't reach alpha
+pub(super) const FUTILITY_MAX_DEPTH: i32 = 3;
+
+/// Late move pruning skips the remaining quiet moves once this many moves have been searched
+pub(super) const LMP_MAX_DEPTH: i32 = 3;
+
+/// Null move pruning is only tried with at least this much depth left
+pub(super) const NMP_MIN_DEPTH: i32 = 3;
+/// Null move cutoffs are verified if the side to move has no more than this much material
+/// besides pawns, since zugzwang is more likely
+pub(super) const NMP_VERIFY_MATERIAL: i32 = PIECE_VALUES[3];
+
+/// Late move reductions by depth and move number
+static LMR_TABLE: LazyLock<[[i32; 64]; 64]> = LazyLock::new(|| {
+    let mut table = [[0; 64]; 64];
+    for (depth, row) in table.iter_mut().enumerate().skip(1) {
+        for (moves, reduction) in row.iter_mut().enumerate().skip(1) {
+            *reduction = (0.75 + (depth as f64).ln() * (moves as f64).ln() / 2.25) as i32;
+        }
+    }
+    table
+});
+
+/// Get the futility margin for the depth left
+pub(super) fn futility_margin(depth: i32) -> i32 {
+    100 + 100 * depth
+}
+
+/// Get the number of moves searched before late move pruning starts
+pub(super) fn lmp_threshold(depth: i32) -> usize {
+    3 + (depth * depth) as usize
+}
+
+/// Get the null move depth reduction, which grows with depth and with how far the eval beats beta
+pub(super) fn null_move_reduction(depth: i32, eval: i32, beta: i32) -> i32 {
+    3 + depth / 4 + ((eval - beta) / 200).min(3)
+}
+
+/// Get the late move reduction for a move
+pub(super) fn lmr_reduction(depth: i32, moves: usize) -> i32 {
+    LMR_TABLE[(depth as usize).min(63)][moves.min(63)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lmr_table() {
+        // Early moves and shallow depths aren't reduced
+        assert_eq!(lmr_reduction(1, 1), 0);
+        assert_eq!(lmr_reduction(3, 1), 0);
+
+        // Reductions grow with depth and move number
+        for depth in 1..63 {
+            for moves in 1..63 {
+                assert!(lmr_reduction(depth, moves) <= lmr_reduction(depth + 1, moves));
+                assert!(lmr_reduction(depth, moves) <= lmr_reduction(depth, moves + 1));
+            }
+        }
+        assert!(lmr_reduction(10, 20) >= 2);
+        assert_eq!(lmr_reduction(100, 100), lmr_reduction(63, 63));
+    }
+}
@@ -0,0 +1,163 @@
+use super::{INF, MATE, MAX_PLY, Searcher};
+use crate::{
+    eval::evaluate,
+    movepick::{capture_gain, mvv_lva},
+};
+use chess::{mv::PromoPiece, position::Position};
+
+/// Captures that can't raise the score to alpha even with this much to spare are skipped
+const DELTA_MARGIN: i32 = 200;
+/// Captures that lose more material than this by static exchange are skipped
+const SEE_THRESHOLD: i32 = 0;
+
+impl Searcher<'_> {
+    /// Quiescence search, only looking at captures and promotions until the position is quiet
+    /// In check every evasion is searched, since standing pat isn't possible
+    pub(super) fn qsearch(&mut self, pos: &Position, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.pv_len[ply] = ply;
+        self.nodes += 1;
+        self.check_stop();
+        if self.stopped {
+            return 0;
+        }
+
+        if ply >= MAX_PLY - 1 {
+            return evaluate(pos);
+        }
+
+        let in_check = pos.in_check();
+        let mut stand_pat = -INF;
+        let mut moves = if in_check {
+            pos.check_evasions()
+        } else {
+            stand_pat = evaluate(pos);
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+
+            pos.captures_and_promotions()
+                .into_iter()
+                .filter(|mv| mv.promo.is_none() || mv.promo == Some(PromoPiece::Queen))
+                .collect()
+        };
+        moves.sort_by_cached_key(|mv| -mvv_lva(pos, mv));
+
+        let mut best = stand_pat;
+        let mut legal = 0;
+
+        for mv in moves {
+            if !in_check {
+                // Delta pruning
+                if stand_pat + capture_gain(pos, &mv) + DELTA_MARGIN <= alpha {
+                    continue;
+                }
+
+                // Losing captures
+                if !pos.see_ge(&mv, SEE_THRESHOLD) {
+                    continue;
+                }
+            }
+
+            let mut npos = *pos;
+            if !npos.makemove(&mv) {
+                continue;
+            }
+            legal += 1;
+
+            let score = -self.qsearch(&npos, ply + 1, -beta, -alpha);
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                if score > alpha {
+                    alpha = score;
+                    self.update_pv(ply, mv);
+                    if score >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+
+        // Checkmate
+        if in_check && legal == 0 {
+            return -MATE + ply as i32;
+        }
+
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{limits::Limits, options::SearchOptions, search::search, tt::TranspositionTable};
+    use chess::mv::Mv;
+    use std::sync::atomic::AtomicBool;
+
+    /// Run a quiescence search with a full window
+    fn qsearch(fen: &str) -> (i32, u64) {
+        let pos = Position::from_fen(fen);
+        let limits = Limits::default();
+        let mut tt = TranspositionTable::new(1);
+        let options = SearchOptions::default();
+        let stop = AtomicBool::default();
+        let mut searcher = Searcher::new(&limits, &options, &stop, &pos, &mut tt);
+        let score = searcher.qsearch(&pos, 0, -INF, INF);
+        (score, searcher.nodes)
+    }
+
+    #[test]
+    fn test_quiet_stands_pat() {
+        let fen = "startpos";
+        let (score, nodes) = qsearch(fen);
+        assert_eq!(score, evaluate(&Position::from_fen(fen)));
+        assert_eq!(nodes, 1);
+    }
+
+    #[test]
+    fn test_resolves_captures() {
+        // A free rook is taken
+        let fen = "4k3/8/8/3r4/8/8/8/3QK3 w - - 0 1";
+        let (score, _) = qsearch(fen);
+        assert!(score > evaluate(&Position::from_fen(fen)) + 400);
+
+        // A defended pawn isn't worth the queen
+        let fen = "4k3/4p3/3p4/8/8/8/8/3QK3 w - - 0 1";
+        let (score, _) = qsearch(fen);
+        assert_eq!(score, evaluate(&Position::from_fen(fen)));
+    }
+
+    #[test]
+    fn test_checkmate() {
+        // Back rank mate, with no evasions
+        let (score, _) = qsearch("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1");
+        assert_eq!(score, -MATE);
+
+        // In check with an evasion, so not mate
+        let (score, _) = qsearch("R5k1/5pp1/8/8/8/8/8/6K1 b - - 0 1");
+        assert!(score > -MATE + MAX_PLY as i32);
+    }
+
+    #[test]
+    fn test_horizon() {
+        // Without a quiescence search, a one ply search grabs the defended pawn
+        let limits = Limits {
+            depth: Some(1),
+            ..Default::default()
+        };
+        let pos = Position::from_fen("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1");
+        let bestmove = search(
+            &pos,
+            &limits,
+            &SearchOptions::default(),
+            &AtomicBool::default(),
+            &mut TranspositionTable::new(1),
+            &mut |_| {},
+        );
+        assert_ne!(bestmove, Some(Mv::from_string("d1d5")));
+    }
+}
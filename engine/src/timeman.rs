@@ -0,0 +1,199 @@
+use crate::limits::Limits;
+use chess::Side;
+use std::time::{Duration, Instant};
+
+/// The default time kept back each move for communication lag, in milliseconds
+pub const DEFAULT_MOVE_OVERHEAD: u64 = 10;
+/// The largest move overhead in milliseconds
+pub const MAX_MOVE_OVERHEAD: u64 = 5000;
+
+/// The number of moves the clock is shared between when there's no "movestogo"
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+/// The hard limit is this many times the soft limit, if there's enough time left
+const HARD_RATIO: u64 = 4;
+/// The most the soft limit can be stretched by
+const MAX_SCALE: f64 = 2.5;
+/// An iteration is expected to take this many times longer than the one before
+const ITERATION_GROWTH: u32 = 2;
+
+/// Decides how long to spend on a move
+#[derive(Debug, Clone)]
+pub struct TimeManager {
+    start: Instant,
+    /// Don't start another iteration after this, before scaling
+    soft: Option<Duration>,
+    /// Stop the search after this
+    hard: Option<Duration>,
+}
+
+impl TimeManager {
+    /// Work out the time to spend from the limits, starting the clock now
+    /// The move overhead is kept back from the clock in milliseconds
+    #[must_use]
+    pub fn new(limits: &Limits, side: Side, overhead: u64) -> Self {
+        let (soft, hard) = if limits.infinite {
+            (None, None)
+        } else if let Some(movetime) = limits.movetime {
+            // Use all of a fixed move time
+            (None, Some(movetime.saturating_sub(overhead).max(1)))
+        } else if let Some(time) = limits.time[side as usize] {
+            let inc = limits.inc[side as usize].unwrap_or(0);
+            let left = time.saturating_sub(overhead).max(1);
+            let moves = limits
+                .movestogo
+                .map_or(DEFAULT_MOVES_TO_GO, u64::from)
+                .max(1);
+
+            // Never use more than most of the clock, whatever the increment
+            let hard = ((left / moves + inc) * HARD_RATIO).min(left * 4 / 5).max(1);
+            let soft = (left / moves + inc * 3 / 4).min(hard);
+            (Some(soft), Some(hard))
+        } else {
+            (None, None)
+        };
+
+        Self {
+            start: Instant::now(),
+            soft: soft.map(Duration::from_millis),
+            hard: hard.map(Duration::from_millis),
+        }
+    }
+
+    /// Get the time since the search started
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Has the hard limit been reached?
+    #[must_use]
+    pub fn out_of_time(&self) -> bool {
+        self.hard.is_some_and(|hard| self.elapsed() >= hard)
+    }
+
+    /// Should iterative deepening stop after an iteration?
+    /// The soft limit is stretched by the scale, and an iteration isn't started if it's
+    /// unlikely to finish before the hard limit
+    #[must_use]
+    pub fn should_stop(&self, scale: f64, last_iteration: Duration) -> bool {
+        let elapsed = self.elapsed();
+        let Some(soft) = self.soft else {
+            return self.out_of_time();
+        };
+
+        elapsed >= soft.mul_f64(scale.clamp(0.0, MAX_SCALE))
+            || self
+                .hard
+                .is_some_and(|hard| elapsed + last_iteration * ITERATION_GROWTH >= hard)
+    }
+}
+
+/// Get how much to stretch the soft limit by
+/// More time is given when the best move keeps changing or the score is dropping
+#[must_use]
+pub fn time_scale(best_move_changes: f64, score_drop: i32) -> f64 {
+    let instability = 1.0 + best_move_changes / 2.0;
+    let falling = 1.0 + f64::from(score_drop.clamp(0, 100)) / 200.0;
+    (instability * falling).min(MAX_SCALE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Option<Duration> {
+        Some(Duration::from_millis(ms))
+    }
+
+    #[test]
+    fn test_movetime() {
+        let limits = Limits {
+            movetime: Some(1000),
+            ..Default::default()
+        };
+        let tm = TimeManager::new(&limits, Side::White, 50);
+        assert_eq!(tm.soft, None);
+        assert_eq!(tm.hard, ms(950));
+
+        // Fixed time searches don't stop early
+        assert!(!tm.should_stop(1.0, Duration::from_millis(600)));
+    }
+
+    #[test]
+    fn test_clock() {
+        let limits = Limits {
+            time: [Some(60_000), Some(1000)],
+            inc: [Some(1000); 2],
+            ..Default::default()
+        };
+        let tm = TimeManager::new(&limits, Side::White, 0);
+        assert_eq!(tm.soft, ms(2000 + 750));
+        assert_eq!(tm.hard, ms(3000 * 4));
+
+        // Little time left caps the hard limit
+        let tm = TimeManager::new(&limits, Side::Black, 0);
+        assert_eq!(tm.hard, ms(800));
+        assert!(tm.soft <= tm.hard);
+
+        // The last move before the time control can use most of the clock
+        let limits = Limits {
+            movestogo: Some(1),
+            ..limits
+        };
+        let tm = TimeManager::new(&limits, Side::White, 0);
+        assert_eq!(tm.hard, ms(48_000));
+    }
+
+    #[test]
+    fn test_overhead() {
+        let limits = Limits {
+            time: [Some(5), Some(5)],
+            ..Default::default()
+        };
+
+        // Even with less time than the overhead there's something to search with
+        let tm = TimeManager::new(&limits, Side::White, DEFAULT_MOVE_OVERHEAD);
+        assert_eq!(tm.hard, ms(1));
+        assert!(tm.soft <= tm.hard);
+    }
+
+    #[test]
+    fn test_no_limits() {
+        for limits in [
+            Limits::default(),
+            Limits {
+                infinite: true,
+                time: [Some(1000), Some(1000)],
+                ..Default::default()
+            },
+        ] {
+            let tm = TimeManager::new(&limits, Side::White, 0);
+            assert!(!tm.out_of_time());
+            assert!(!tm.should_stop(1.0, Duration::from_secs(1000)));
+        }
+    }
+
+    #[test]
+    fn test_should_stop() {
+        let tm = TimeManager {
+            start: Instant::now(),
+            soft: ms(10_000),
+            hard: ms(40_000),
+        };
+        assert!(!tm.should_stop(1.0, Duration::ZERO));
+        assert!(tm.should_stop(0.0, Duration::ZERO));
+
+        // The next iteration wouldn't finish in time
+        assert!(tm.should_stop(1.0, Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn test_time_scale() {
+        assert_eq!(time_scale(0.0, 0), 1.0);
+        assert_eq!(time_scale(0.0, -50), 1.0);
+        assert!(time_scale(1.0, 0) > 1.0);
+        assert!(time_scale(0.0, 50) > 1.0);
+        assert!(time_scale(0.0, 100) > time_scale(0.0, 50));
+        assert_eq!(time_scale(10.0, 1000), MAX_SCALE);
+    }
+}
@@ -0,0 +1,282 @@
+use crate::search::{MATE, MAX_PLY};
+use chess::mv::Mv;
+use std::mem::size_of;
+
+/// The default table size in megabytes
+pub const DEFAULT_HASH_MB: usize = 16;
+/// The largest table size in megabytes
+pub const MAX_HASH_MB: usize = 4096;
+/// Entries per bucket
+const BUCKET_SIZE: usize = 4;
+
+/// How the stored score relates to the true score
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Bound {
+    /// The score is exact
+    Exact,
+    /// The true score is at least the stored score
+    Lower,
+    /// The true score is at most the stored score
+    Upper,
+}
+
+/// A single stored search result
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Entry {
+    pub key: u64,
+    pub mv: Option<Mv>,
+    pub depth: i32,
+    pub bound: Bound,
+    /// Score relative to this position, so mate scores don't depend on the ply it was found at
+    score: i32,
+    pub eval: i32,
+    age: u8,
+}
+
+impl Entry {
+    /// Get the score, with mate scores adjusted to the ply given
+    #[must_use]
+    pub fn score(&self, ply: usize) -> i32 {
+        score_from_tt(self.score, ply)
+    }
+}
+
+type Bucket = [Option<Entry>; BUCKET_SIZE];
+
+/// Turn a mate score relative to the root into one relative to the current position
+#[must_use]
+pub fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score + ply as i32
+    } else if score <= -MATE + MAX_PLY as i32 {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+/// Turn a mate score relative to the current position into one relative to the root
+#[must_use]
+pub fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score - ply as i32
+    } else if score <= -MATE + MAX_PLY as i32 {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+/// A hash table of search results shared between searches
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    /// The generation of the current search, used to replace entries from old searches first
+    age: u8,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_HASH_MB)
+    }
+}
+
+impl TranspositionTable {
+    /// Create a table using about the given number of megabytes
+    #[must_use]
+    pub fn new(mb: usize) -> Self {
+        let count = (mb.clamp(1, MAX_HASH_MB) * 1024 * 1024 / size_of::<Bucket>()).max(1);
+        Self {
+            buckets: vec![[None; BUCKET_SIZE]; count],
+            age: 0,
+        }
+    }
+
+    /// Get the number of entries the table can hold
+    #[cfg(test)]
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * BUCKET_SIZE
+    }
+
+    /// Remove every entry
+    pub fn clear(&mut self) {
+        self.buckets.fill([None; BUCKET_SIZE]);
+        self.age = 0;
+    }
+
+    /// Start a new search, making existing entries older
+    pub fn new_search(&mut self) {
+        self.age = self.age.wrapping_add(1);
+    }
+
+    /// Get the bucket a key belongs in
+    fn index(&self, key: u64) -> usize {
+        ((u128::from(key) * self.buckets.len() as u128) >> 64) as usize
+    }
+
+    /// Look up a position by its hash key
+    #[must_use]
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        self.buckets[self.index(key)]
+            .iter()
+            .flatten()
+            .find(|entry| entry.key == key)
+            .copied()
+    }
+
+    /// Store a search result
+    /// The score is relative to the root, and is adjusted by the ply for storage
+    #[allow(clippy::too_many_arguments)]
+    pub fn store(
+        &mut self,
+        key: u64,
+        mv: Option<Mv>,
+        depth: i32,
+        bound: Bound,
+        score: i32,
+        eval: i32,
+        ply: usize,
+    ) {
+        let age = self.age;
+        let idx = self.index(key);
+        let bucket = &mut self.buckets[idx];
+
+        // Replace the same position if it's there, then empty slots,
+        // then the shallowest entry with entries from old searches counting as shallower
+        let worth = |slot: &Option<Entry>| match slot {
+            Some(entry) if entry.key == key => i32::MIN,
+            Some(entry) => entry.depth - 8 * i32::from(age.wrapping_sub(entry.age)),
+            None => i32::MIN + 1,
+        };
+        let slot = bucket
+            .iter_mut()
+            .min_by_key(|slot| worth(slot))
+            .expect("Empty bucket");
+
+        // Keep the old move if the new result doesn't have one
+        let mv = match slot {
+            Some(old) if old.key == key => mv.or(old.mv),
+            _ => mv,
+        };
+
+        *slot = Some(Entry {
+            key,
+            mv,
+            depth,
+            bound,
+            score: score_to_tt(score, ply),
+            eval,
+            age,
+        });
+    }
+
+    /// Estimate how full the table is in permille, only counting entries from the current search
+    #[must_use]
+    pub fn hashfull(&self) -> u32 {
+        let sample = self.buckets.iter().take(1000 / BUCKET_SIZE);
+        let total = sample.len() * BUCKET_SIZE;
+        let used = sample
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.age == self.age)
+            .count();
+        (used * 1000 / total) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_probe() {
+        let mut tt = TranspositionTable::new(1);
+        let mv = Mv::from_string("e2e4");
+        assert_eq!(tt.probe(42), None);
+
+        tt.store(42, Some(mv), 5, Bound::Exact, 17, 10, 3);
+        let entry = tt.probe(42).unwrap();
+        assert_eq!(entry.mv, Some(mv));
+        assert_eq!(entry.depth, 5);
+        assert_eq!(entry.bound, Bound::Exact);
+        assert_eq!(entry.score(0), 17);
+        assert_eq!(entry.eval, 10);
+
+        // Storing again without a move keeps the old one
+        tt.store(42, None, 6, Bound::Upper, -4, 10, 3);
+        let entry = tt.probe(42).unwrap();
+        assert_eq!(entry.mv, Some(mv));
+        assert_eq!(entry.depth, 6);
+
+        tt.clear();
+        assert_eq!(tt.probe(42), None);
+    }
+
+    #[test]
+    fn test_mate_scores() {
+        // Mate in 3 plies from a position 5 plies from the root
+        let mut tt = TranspositionTable::new(1);
+        tt.store(1, None, 1, Bound::Exact, MATE - 8, 0, 5);
+        let entry = tt.probe(1).unwrap();
+        assert_eq!(entry.score(5), MATE - 8);
+        assert_eq!(entry.score(1), MATE - 4);
+
+        tt.store(2, None, 1, Bound::Exact, -MATE + 8, 0, 5);
+        assert_eq!(tt.probe(2).unwrap().score(3), -MATE + 6);
+
+        for score in [-300, 0, 1234] {
+            assert_eq!(score_to_tt(score, 10), score);
+            assert_eq!(score_from_tt(score, 10), score);
+        }
+    }
+
+    #[test]
+    fn test_replacement() {
+        let mut tt = TranspositionTable::new(1);
+
+        // Small keys all go in the first bucket
+        for n in 0..BUCKET_SIZE as u64 {
+            tt.store(n, None, 10 + n as i32, Bound::Exact, 0, 0, 0);
+        }
+        assert!((0..BUCKET_SIZE as u64).all(|n| tt.probe(n).is_some()));
+
+        // The shallowest entry goes first
+        tt.store(100, None, 1, Bound::Exact, 0, 0, 0);
+        assert_eq!(tt.probe(0), None);
+        assert!(tt.probe(100).is_some());
+
+        // Entries from old searches go before deeper ones from this search
+        tt.new_search();
+        tt.store(200, None, 5, Bound::Exact, 0, 0, 0);
+        tt.store(300, None, 5, Bound::Exact, 0, 0, 0);
+        assert!(tt.probe(200).is_some());
+        assert!(tt.probe(300).is_some());
+        assert_eq!(tt.probe(1), None);
+        assert!(tt.probe(3).is_some());
+    }
+
+    #[test]
+    fn test_hashfull() {
+        let mut tt = TranspositionTable::new(1);
+        assert_eq!(tt.hashfull(), 0);
+
+        for key in 0u64.. {
+            if tt.hashfull() >= 500 {
+                break;
+            }
+            tt.store(
+                key.wrapping_mul(0x9E37_79B9_7F4A_7C15),
+                None,
+                1,
+                Bound::Exact,
+                0,
+                0,
+                0,
+            );
+        }
+        assert!(tt.hashfull() >= 500);
+
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+    }
+}
@@ -0,0 +1,680 @@
+use crate::{
+    input::Signals,
+    limits::Limits,
+    options::SearchOptions,
+    search::{Report, is_mate_score, mate_in, search},
+    timeman::{DEFAULT_MOVE_OVERHEAD, MAX_MOVE_OVERHEAD},
+    tt::{Bound, DEFAULT_HASH_MB, MAX_HASH_MB, TranspositionTable},
+};
+use chess::{Side, mv::Mv, position::Position};
+use std::{
+    io::Write,
+    sync::{Arc, atomic::Ordering},
+    thread,
+    time::Duration,
+};
+
+/// Find the legal move matching a string such as "e2e4"
+/// Returns None rather than panicking if the string isn't a legal move
+#[must_use]
+pub fn parse_move(pos: &Position, movestr: &str) -> Option<Mv> {
+    pos.legal_moves()
+        .into_iter()
+        .find(|mv| mv.to_string() == movestr)
+}
+
+/// Parse the limits from the arguments of a "go" command
+#[must_use]
+pub fn parse_go(pos: &Position, args: &[&str]) -> Limits {
+    let mut limits = Limits::default();
+    let mut idx = 0;
+
+    while idx < args.len() {
+        let value = args.get(idx + 1).copied().unwrap_or("");
+        let mut used = true;
+        match args[idx] {
+            "wtime" => limits.time[Side::White as usize] = value.parse().ok(),
+            "btime" => limits.time[Side::Black as usize] = value.parse().ok(),
+            "winc" => limits.inc[Side::White as usize] = value.parse().ok(),
+            "binc" => limits.inc[Side::Black as usize] = value.parse().ok(),
+            "movestogo" => limits.movestogo = value.parse().ok(),
+            "movetime" => limits.movetime = value.parse().ok(),
+            "depth" => limits.depth = value.parse().ok(),
+            "nodes" => limits.nodes = value.parse().ok(),
+            "mate" => limits.mate = value.parse().ok(),
+            "infinite" => {
+                limits.infinite = true;
+                used = false;
+            }
+            "searchmoves" => {
+                // The moves run until the next keyword
+                while let Some(mv) = args.get(idx + 1).and_then(|word| parse_move(pos, word)) {
+                    limits.searchmoves.push(mv);
+                    idx += 1;
+                }
+                used = false;
+            }
+            _ => used = false,
+        }
+        idx += if used { 2 } else { 1 };
+    }
+
+    limits
+}
+
+/// Write a line to the GUI
+pub fn send_line<W: Write>(out: &mut W, line: &str) {
+    writeln!(out, "{line}").expect("Failed to write output");
+    out.flush().expect("Failed to flush output");
+}
+
+/// The state of the UCI front-end
+pub struct Uci<W: Write> {
+    pos: Position,
+    tt: TranspositionTable,
+    options: SearchOptions,
+    /// Shared with the input thread, which raises the stop flag when "stop" or "quit" arrives
+    signals: Arc<Signals>,
+    out: W,
+}
+
+impl<W: Write> Uci<W> {
+    /// Create a new front-end writing to the output given
+    #[must_use]
+    pub fn new(out: W, signals: Arc<Signals>) -> Self {
+        Self {
+            pos: Position::from_fen("startpos"),
+            tt: TranspositionTable::default(),
+            options: SearchOptions::default(),
+            signals,
+            out,
+        }
+    }
+
+    /// Write a line to the GUI
+    fn send(&mut self, line: &str) {
+        send_line(&mut self.out, line);
+    }
+
+    /// Handle a single line of input
+    /// Returns false when the engine should quit
+    pub fn handle(&mut self, line: &str) -> bool {
+        let words = line.split_whitespace().collect::<Vec<&str>>();
+        let Some((command, args)) = words.split_first() else {
+            return true;
+        };
+
+        match *command {
+            "uci" => {
+                self.send("id name Potato");
+                self.send("id author EngineProgramming");
+                self.send(&format!(
+                    "option name Hash type spin default {DEFAULT_HASH_MB} min 1 max {MAX_HASH_MB}"
+                ));
+                self.send("option name Clear Hash type button");
+                self.send(&format!(
+                    "option name Move Overhead type spin default {DEFAULT_MOVE_OVERHEAD} min 0 max {MAX_MOVE_OVERHEAD}"
+                ));
+                for name in SearchOptions::NAMES {
+                    self.send(&format!("option name {name} type check default true"));
+                }
+                self.send("uciok");
+            }
+            "isready" => self.send("readyok"),
+            "ucinewgame" => {
+                self.pos = Position::from_fen("startpos");
+                self.tt.clear();
+            }
+            "position" => self.position(args),
+            "setoption" => self.setoption(args),
+            "go" => self.go(args),
+            // The search has already been stopped, so get ready for the next one
+            "stop" => self.signals.stop.store(false, Ordering::Relaxed),
+            "debug" | "ponderhit" => {}
+            "quit" => return false,
+            _ => self.send(&format!("info string Unknown command \"{command}\"")),
+        }
+
+        true
+    }
+
+    /// Handle "position startpos|fen ... moves ..."
+    fn position(&mut self, args: &[&str]) {
+        let moves_idx = args
+            .iter()
+            .position(|word| *word == "moves")
+            .unwrap_or(args.len());
+
+        let fen = match args.first() {
+            Some(&"startpos") => "startpos".to_string(),
+            Some(&"fen") => args[1..moves_idx].join(" "),
+            _ => {
+                self.send("info string Expected \"startpos\" or \"fen\"");
+                return;
+            }
+        };
+        let Some(mut pos) = Position::try_from_fen(&fen) else {
+            self.send(&format!("info string Invalid FEN \"{fen}\""));
+            return;
+        };
+
+        for movestr in args.iter().skip(moves_idx + 1) {
+            let Some(mv) = parse_move(&pos, movestr) else {
+                self.send(&format!("info string Illegal move \"{movestr}\""));
+                break;
+            };
+            let success = pos.makemove(&mv);
+            debug_assert!(success);
+        }
+
+        self.pos = pos;
+    }
+
+    /// Handle "setoption name <id> [value <x>]"
+    /// Option names can contain spaces and are case insensitive
+    fn setoption(&mut self, args: &[&str]) {
+        if args.first() != Some(&"name") {
+            self.send("info string Expected \"name\"");
+            return;
+        }
+        let value_idx = args
+            .iter()
+            .position(|word| *word == "value")
+            .unwrap_or(args.len());
+        let name = args[1..value_idx].join(" ");
+        let value = args.get(value_idx + 1..).unwrap_or_default().join(" ");
+
+        match name.to_lowercase().as_str() {
+            "hash" => match value.parse::<usize>() {
+                Ok(mb) if (1..=MAX_HASH_MB).contains(&mb) => self.tt = TranspositionTable::new(mb),
+                _ => self.send(&format!("info string Invalid Hash value \"{value}\"")),
+            },
+            "clear hash" => self.tt.clear(),
+            "move overhead" => match value.parse::<u64>() {
+                Ok(ms) if ms <= MAX_MOVE_OVERHEAD => self.options.move_overhead = ms,
+                _ => self.send(&format!(
+                    "info string Invalid Move Overhead value \"{value}\""
+                )),
+            },
+            _ if SearchOptions::NAMES
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(&name)) =>
+            {
+                match value.parse::<bool>() {
+                    Ok(on) => {
+                        self.options.set(&name, on);
+                    }
+                    Err(_) => self.send(&format!("info string Invalid {name} value \"{value}\"")),
+                }
+            }
+            _ => self.send(&format!("info string Unknown option \"{name}\"")),
+        }
+    }
+
+    /// Handle "go" and its limits
+    fn go(&mut self, args: &[&str]) {
+        let pos = self.pos;
+        let limits = parse_go(&pos, args);
+        let out = &mut self.out;
+        self.signals.searching.store(true, Ordering::Relaxed);
+        let bestmove = search(
+            &pos,
+            &limits,
+            &self.options,
+            &self.signals.stop,
+            &mut self.tt,
+            &mut |report| send_line(out, &info_line(report)),
+        );
+
+        // An infinite search doesn't give its move until told to stop, even if it finishes early
+        while limits.infinite && !self.signals.stop.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        match bestmove {
+            Some(mv) => self.send(&format!("bestmove {mv}")),
+            None => self.send("bestmove 0000"),
+        }
+        self.signals.searching.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Format a search report as a UCI info line
+#[must_use]
+pub fn info_line(report: &Report) -> String {
+    let nps = report.nodes * 1000 / report.time.max(1);
+    let pv = report
+        .pv
+        .iter()
+        .map(Mv::to_string)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut score = if is_mate_score(report.score) {
+        format!("mate {}", mate_in(report.score))
+    } else {
+        format!("cp {}", report.score)
+    };
+    match report.bound {
+        Bound::Exact => {}
+        Bound::Lower => score.push_str(" lowerbound"),
+        Bound::Upper => score.push_str(" upperbound"),
+    }
+    format!(
+        "info depth {} score {} nodes {} nps {} hashfull {} time {} pv {}",
+        report.depth, score, report.nodes, nps, report.hashfull, report.time, pv
+    )
+}
+
+/// Handle the commands that can't wait for a search, called from the input thread
+/// The stop flag is raised as soon as "stop" or "quit" is read, and "isready" is answered
+/// straight away during a search, which counts as running as soon as its "go" is read
+/// Returns whether the line should still be passed on
+pub fn intercept<W: Write>(line: &str, signals: &Signals, out: &mut W) -> bool {
+    match line.split_whitespace().next() {
+        Some("stop" | "quit") => {
+            signals.stop.store(true, Ordering::Relaxed);
+            true
+        }
+        Some("isready") if signals.searching.load(Ordering::Relaxed) => {
+            send_line(out, "readyok");
+            false
+        }
+        Some("go") => {
+            signals.searching.store(true, Ordering::Relaxed);
+            true
+        }
+        _ => true,
+    }
+}
+
+/// Run the UCI loop until "quit" or the end of the input
+pub fn run<W: Write>(input: impl IntoIterator<Item = String>, out: W, signals: Arc<Signals>) {
+    let mut uci = Uci::new(out, signals);
+
+    for line in input {
+        if !uci.handle(&line) {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        input::{spawn_input, tests::SharedOutput},
+        search::MATE,
+    };
+    use std::io::BufReader;
+
+    /// Run a script of commands and get the output lines
+    fn run_script(script: &str) -> Vec<String> {
+        let mut out = Vec::new();
+        run(script.lines().map(str::to_string), &mut out, Arc::default());
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_handshake() {
+        let lines = run_script("uci\nisready\nquit\nisready\n");
+        assert_eq!(
+            lines,
+            [
+                "id name Potato",
+                "id author EngineProgramming",
+                "option name Hash type spin default 16 min 1 max 4096",
+                "option name Clear Hash type button",
+                "option name Move Overhead type spin default 10 min 0 max 5000",
+                "option name NullMove type check default true",
+                "option name LMR type check default true",
+                "option name ReverseFutility type check default true",
+                "option name Futility type check default true",
+                "option name LateMovePruning type check default true",
+                "option name Razoring type check default true",
+                "uciok",
+                "readyok"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_position() {
+        let mut uci = Uci::new(Vec::new(), Arc::default());
+
+        uci.handle("position startpos moves e2e4 e7e5 g1f3");
+        assert_eq!(
+            uci.pos.get_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+
+        uci.handle("position fen 4k3/P7/8/8/8/8/8/4K3 w - - 0 1 moves a7a8q");
+        assert_eq!(uci.pos.get_fen(), "Q3k3/8/8/8/8/8/8/4K3 b - - 0 1");
+
+        uci.handle("position fen 4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(uci.pos.get_fen(), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert!(uci.out.is_empty());
+    }
+
+    #[test]
+    fn test_position_errors() {
+        let mut uci = Uci::new(Vec::new(), Arc::default());
+
+        // Moves stop at the first illegal one
+        uci.handle("position startpos moves e2e4 e2e4 e7e5");
+        assert_eq!(
+            uci.pos.get_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+
+        // Invalid input leaves the position alone
+        for line in [
+            "position fen 4k3/8/8/8/8/8/8/8 w - - 0 1",
+            "position fen P3k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "position fen 4k3/8/8/3pP3/8/8/8/4K3 w - e8 0 1",
+            "position fen 4k3/8/8/8/8/8/8/K3R3 w - - 0 1",
+            "position fen 4k3/8/8/8/8/8/8/4K3 w K - 0 1",
+            "position fen",
+            "position",
+        ] {
+            uci.handle("position startpos moves e2e4");
+            uci.handle(line);
+            assert_eq!(
+                uci.pos.get_fen(),
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+                "{line}"
+            );
+        }
+
+        uci.handle("position startpos moves e2");
+        assert_eq!(uci.pos, Position::from_fen("startpos"));
+
+        let output = String::from_utf8(uci.out).unwrap();
+        assert!(output.lines().all(|line| line.starts_with("info string")));
+    }
+
+    #[test]
+    fn test_parse_go() {
+        let pos = Position::from_fen("startpos");
+
+        let args = "wtime 1000 btime 2000 winc 10 binc 20 movestogo 30"
+            .split_whitespace()
+            .collect::<Vec<_>>();
+        let limits = parse_go(&pos, &args);
+        assert_eq!(limits.time, [Some(1000), Some(2000)]);
+        assert_eq!(limits.inc, [Some(10), Some(20)]);
+        assert_eq!(limits.movestogo, Some(30));
+
+        let args = "infinite searchmoves e2e4 d2d4 e2e5 depth 5 nodes 100 mate 3 movetime 50"
+            .split_whitespace()
+            .collect::<Vec<_>>();
+        let limits = parse_go(&pos, &args);
+        assert!(limits.infinite);
+        assert_eq!(
+            limits.searchmoves,
+            [Mv::from_string("e2e4"), Mv::from_string("d2d4")]
+        );
+        assert_eq!(limits.depth, Some(5));
+        assert_eq!(limits.nodes, Some(100));
+        assert_eq!(limits.mate, Some(3));
+        assert_eq!(limits.movetime, Some(50));
+    }
+
+    #[test]
+    fn test_go() {
+        let lines = run_script(
+            "position startpos\ngo depth 1\nposition startpos moves e2e4\ngo depth 1 searchmoves e7e5\n",
+        );
+        let bestmoves = lines
+            .iter()
+            .filter(|line| line.starts_with("bestmove "))
+            .collect::<Vec<_>>();
+        assert_eq!(bestmoves.len(), 2);
+        assert_eq!(bestmoves[1], "bestmove e7e5");
+        assert!(lines[0].starts_with("info depth 1 "));
+
+        // Checkmate
+        let lines = run_script("position startpos moves f2f3 e7e5 g2g4 d8h4\ngo\n");
+        assert_eq!(lines, ["bestmove 0000"]);
+    }
+
+    #[test]
+    fn test_info_line() {
+        let report = Report {
+            depth: 3,
+            score: -25,
+            bound: Bound::Exact,
+            nodes: 5000,
+            time: 250,
+            hashfull: 12,
+            pv: vec![Mv::from_string("e2e4"), Mv::from_string("e7e5")],
+        };
+        assert_eq!(
+            info_line(&report),
+            "info depth 3 score cp -25 nodes 5000 nps 20000 hashfull 12 time 250 pv e2e4 e7e5"
+        );
+
+        let report = Report {
+            score: MATE - 3,
+            ..report
+        };
+        assert!(info_line(&report).contains(" score mate 2 "));
+        let report = Report {
+            score: -MATE + 2,
+            ..report
+        };
+        assert!(info_line(&report).contains(" score mate -1 "));
+
+        // Failed aspiration windows give bounds
+        let report = Report {
+            score: 40,
+            bound: Bound::Lower,
+            ..report
+        };
+        assert!(info_line(&report).contains(" score cp 40 lowerbound nodes "));
+        let report = Report {
+            bound: Bound::Upper,
+            ..report
+        };
+        assert!(info_line(&report).contains(" score cp 40 upperbound nodes "));
+    }
+
+    #[test]
+    fn test_hash_options() {
+        let mut uci = Uci::new(Vec::new(), Arc::default());
+        uci.handle("setoption name Hash value 1");
+        let small = uci.tt.capacity();
+        uci.handle("setoption name Hash value 2");
+        assert_eq!(uci.tt.capacity(), small * 2);
+        assert!(uci.out.is_empty());
+
+        // Invalid values are ignored
+        uci.handle("setoption name Hash value 0");
+        uci.handle("setoption name Hash value lots");
+        uci.handle("setoption name Hash");
+        assert_eq!(uci.tt.capacity(), small * 2);
+
+        uci.out.clear();
+        uci.handle("go depth 4");
+        let output = String::from_utf8(uci.out.clone()).unwrap();
+        let last_info = output
+            .lines()
+            .rfind(|line| line.starts_with("info"))
+            .unwrap();
+        assert!(!last_info.contains(" hashfull 0 "), "{output}");
+
+        // Clearing the table empties it
+        uci.handle("setoption name Clear Hash");
+        uci.out.clear();
+        uci.handle("go depth 1");
+        let output = String::from_utf8(uci.out.clone()).unwrap();
+        assert!(output.contains(" hashfull 0 "), "{output}");
+
+        uci.handle("setoption name Nonsense value 3");
+        let output = String::from_utf8(uci.out).unwrap();
+        assert!(output.ends_with("info string Unknown option \"Nonsense\"\n"));
+    }
+
+    #[test]
+    fn test_move_overhead() {
+        let mut uci = Uci::new(Vec::new(), Arc::default());
+        uci.handle("setoption name Move Overhead value 100");
+        assert_eq!(uci.options.move_overhead, 100);
+
+        uci.handle("setoption name Move Overhead value 100000");
+        assert_eq!(uci.options.move_overhead, 100);
+        assert!(!uci.out.is_empty());
+    }
+
+    #[test]
+    fn test_go_clock() {
+        // A short clock is used sparingly, leaving time for the rest of the game
+        let start = std::time::Instant::now();
+        let lines = run_script("go wtime 1000 btime 1000 winc 0 binc 0\n");
+        assert!(start.elapsed().as_millis() < 800);
+        assert!(lines.last().unwrap().starts_with("bestmove "));
+    }
+
+    #[test]
+    fn test_go_nodes() {
+        // Node limited searches are the same every time
+        let strip_time = |lines: Vec<String>| {
+            lines
+                .into_iter()
+                .map(|line| {
+                    let words = line.split(' ').collect::<Vec<_>>();
+                    words
+                        .iter()
+                        .enumerate()
+                        .filter(|(idx, _)| {
+                            !matches!(words.get(idx.wrapping_sub(1)), Some(&("nps" | "time")))
+                        })
+                        .map(|(_, word)| *word)
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect::<Vec<_>>()
+        };
+        let script = "position startpos moves e2e4\ngo nodes 3000\n";
+        let lines = strip_time(run_script(script));
+        assert!(lines.last().unwrap().starts_with("bestmove "));
+        assert_eq!(lines, strip_time(run_script(script)));
+    }
+
+    #[test]
+    fn test_go_infinite() {
+        let signals = Arc::<Signals>::default();
+        let mut uci = Uci::new(Vec::new(), Arc::clone(&signals));
+
+        // Even a search that finishes straight away waits for the stop
+        let raise = Arc::clone(&signals);
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            raise.stop.store(true, Ordering::Relaxed);
+        });
+        uci.handle("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        uci.handle("go infinite depth 2");
+        stopper.join().unwrap();
+        let output = String::from_utf8(uci.out.clone()).unwrap();
+        assert!(output.ends_with("bestmove a1a8\n"), "{output}");
+
+        // Handling "stop" gets ready for the next search
+        uci.handle("stop");
+        assert!(!signals.stop.load(Ordering::Relaxed));
+        assert!(!signals.searching.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_isready_during_search() {
+        let signals = Arc::<Signals>::default();
+        let out = SharedOutput::default();
+        let (reader, mut writer) = std::io::pipe().unwrap();
+
+        let lines = spawn_input(BufReader::new(reader), {
+            let signals = Arc::clone(&signals);
+            let mut out = out.clone();
+            move |line| intercept(line, &signals, &mut out)
+        });
+        let engine = thread::spawn({
+            let signals = Arc::clone(&signals);
+            let out = out.clone();
+            move || run(lines, out, signals)
+        });
+
+        writeln!(writer, "position startpos\ngo infinite").unwrap();
+        out.wait_for("info depth");
+        writeln!(writer, "isready").unwrap();
+        out.wait_for("readyok");
+        assert!(!out.lines().iter().any(|line| line.starts_with("bestmove")));
+
+        writeln!(writer, "stop").unwrap();
+        out.wait_for("bestmove");
+        writeln!(writer, "isready\nquit").unwrap();
+        engine.join().unwrap();
+
+        let lines = out.lines();
+        let answers = lines
+            .iter()
+            .filter(|line| *line == "readyok" || line.starts_with("bestmove"))
+            .map(|line| &line[..line.find(' ').unwrap_or(line.len())])
+            .collect::<Vec<_>>();
+        assert_eq!(answers, ["readyok", "bestmove", "readyok"]);
+    }
+
+    #[test]
+    fn test_isready_straight_after_go() {
+        let signals = Arc::<Signals>::default();
+        let out = SharedOutput::default();
+        let (reader, mut writer) = std::io::pipe().unwrap();
+
+        let lines = spawn_input(BufReader::new(reader), {
+            let signals = Arc::clone(&signals);
+            let mut out = out.clone();
+            move |line| intercept(line, &signals, &mut out)
+        });
+        let engine = thread::spawn({
+            let signals = Arc::clone(&signals);
+            let out = out.clone();
+            move || run(lines, out, signals)
+        });
+
+        // The "isready" can arrive before the main loop has started the search
+        write!(writer, "position startpos\ngo infinite\nisready\n").unwrap();
+        out.wait_for("readyok");
+        assert!(!out.lines().iter().any(|line| line.starts_with("bestmove")));
+
+        writeln!(writer, "stop\nquit").unwrap();
+        engine.join().unwrap();
+        assert!(out.lines().last().unwrap().starts_with("bestmove"));
+    }
+
+    #[test]
+    fn test_search_options() {
+        let mut uci = Uci::new(Vec::new(), Arc::default());
+        uci.handle("setoption name NullMove value false");
+        uci.handle("setoption name lmr value false");
+        assert!(!uci.options.null_move);
+        assert!(!uci.options.lmr);
+        assert!(uci.options.futility);
+
+        uci.handle("setoption name NullMove value true");
+        assert!(uci.options.null_move);
+        assert!(uci.out.is_empty());
+
+        uci.handle("setoption name Razoring value maybe");
+        assert!(uci.options.razoring);
+        let output = String::from_utf8(uci.out).unwrap();
+        assert_eq!(output, "info string Invalid Razoring value \"maybe\"\n");
+    }
+
+    #[test]
+    fn test_unknown_command() {
+        let lines = run_script("foo bar\n\nisready\n");
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("info string"));
+        assert_eq!(lines[1], "readyok");
+    }
+}
@@ -0,0 +1,625 @@
+use crate::{
+    input::Signals,
+    limits::Limits,
+    options::SearchOptions,
+    search::{Report, is_mate_score, mate_in, search},
+    tt::{Bound, MAX_HASH_MB, TranspositionTable},
+    uci::{parse_move, send_line},
+};
+use chess::{
+    Side,
+    game::{Game, GameResult},
+    mv::Mv,
+    position::Position,
+};
+use std::{
+    io::Write,
+    sync::{Arc, atomic::Ordering},
+};
+
+/// The features we ask the GUI for in reply to "protover 2"
+const FEATURES: &str = "feature ping=1 setboard=1 usermove=1 time=1 draw=0 sigint=0 sigterm=0 reuse=1 analyze=0 colors=0 memory=1 myname=\"Potato\" done=1";
+
+/// The time control set by "level"
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+struct Level {
+    /// Moves per time control, 0 for the whole game
+    mps: u32,
+    /// Base time in milliseconds
+    base: u64,
+    /// Increment in milliseconds
+    inc: u64,
+}
+
+/// Parse a base time from "level", which is in minutes or "minutes:seconds"
+fn parse_base(word: &str) -> Option<u64> {
+    match word.split_once(':') {
+        Some((mins, secs)) => {
+            Some(mins.parse::<u64>().ok()? * 60_000 + secs.parse::<u64>().ok()? * 1000)
+        }
+        None => Some(word.parse::<u64>().ok()? * 60_000),
+    }
+}
+
+/// Format a search report as a line of thinking output
+/// The fields are depth, score in centipawns, time in centiseconds, nodes and the PV
+/// Mate scores are given as 100000 plus the moves to mate, negated if getting mated
+#[must_use]
+pub fn thinking_line(report: &Report) -> String {
+    let score = if is_mate_score(report.score) {
+        let moves = mate_in(report.score);
+        moves.signum() * 100_000 + moves
+    } else {
+        report.score
+    };
+    let pv = report
+        .pv
+        .iter()
+        .map(Mv::to_string)
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "{} {} {} {} {}",
+        report.depth,
+        score,
+        report.time / 10,
+        report.nodes,
+        pv
+    )
+}
+
+/// The state of the XBoard front-end
+pub struct XBoard<W: Write> {
+    game: Game,
+    pos: Position,
+    /// The side the engine plays, or None in force mode
+    engine_side: Option<Side>,
+    /// Print thinking output
+    post: bool,
+    level: Level,
+    /// Time per move in milliseconds, set by "st"
+    movetime: Option<u64>,
+    /// Maximum depth, set by "sd"
+    depth: Option<u32>,
+    /// Clock times in milliseconds, set by "time" and "otim"
+    time: Option<u64>,
+    otim: Option<u64>,
+    tt: TranspositionTable,
+    /// Shared with the input thread, which stops the search on "?", "quit",
+    /// or a command that changes the game
+    signals: Arc<Signals>,
+    out: W,
+}
+
+impl<W: Write> XBoard<W> {
+    /// Create a new front-end writing to the output given
+    #[must_use]
+    pub fn new(out: W, signals: Arc<Signals>) -> Self {
+        let pos = Position::from_fen("startpos");
+        Self {
+            game: Game::new(pos),
+            pos,
+            engine_side: Some(Side::Black),
+            post: false,
+            level: Level::default(),
+            movetime: None,
+            depth: None,
+            time: None,
+            otim: None,
+            tt: TranspositionTable::default(),
+            signals,
+            out,
+        }
+    }
+
+    /// Write a line to the GUI
+    fn send(&mut self, line: &str) {
+        send_line(&mut self.out, line);
+    }
+
+    /// Start a new game from the position given
+    fn set_position(&mut self, pos: Position) {
+        self.game = Game::new(pos);
+        self.pos = pos;
+    }
+
+    /// Handle a single line of input
+    /// Returns false when the engine should quit
+    pub fn handle(&mut self, line: &str) -> bool {
+        let words = line.split_whitespace().collect::<Vec<&str>>();
+        let Some((command, args)) = words.split_first() else {
+            return true;
+        };
+        let arg = |idx: usize| args.get(idx).copied().unwrap_or("");
+
+        match *command {
+            "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer"
+            | "name" | "rating" => {}
+            // The input thread has already stopped any search
+            "?" => {}
+            "protover" => self.send(FEATURES),
+            "ping" => self.send(&format!("pong {}", arg(0))),
+            "new" => {
+                self.set_position(Position::from_fen("startpos"));
+                self.engine_side = Some(Side::Black);
+                self.movetime = None;
+                self.depth = None;
+                self.tt.clear();
+            }
+            "setboard" => match Position::try_from_fen(&args.join(" ")) {
+                Some(pos) => self.set_position(pos),
+                None => self.send("tellusererror Illegal position"),
+            },
+            "usermove" => self.usermove(arg(0)),
+            "go" => {
+                self.engine_side = Some(self.pos.turn);
+                self.think();
+            }
+            "force" | "result" => self.engine_side = None,
+            "white" => self.engine_side = Some(Side::Black),
+            "black" => self.engine_side = Some(Side::White),
+            "level" => match (arg(0).parse(), parse_base(arg(1)), arg(2).parse::<f64>()) {
+                (Ok(mps), Some(base), Ok(inc)) => {
+                    self.level = Level {
+                        mps,
+                        base,
+                        inc: (inc * 1000.0) as u64,
+                    };
+                    self.movetime = None;
+                }
+                _ => self.send(&format!("Error (bad arguments): {line}")),
+            },
+            "st" => match arg(0).parse::<u64>() {
+                Ok(secs) => self.movetime = Some(secs * 1000),
+                Err(_) => self.send(&format!("Error (bad arguments): {line}")),
+            },
+            "sd" => match arg(0).parse() {
+                Ok(depth) => self.depth = Some(depth),
+                Err(_) => self.send(&format!("Error (bad arguments): {line}")),
+            },
+            "time" => self.time = arg(0).parse::<u64>().ok().map(|cs| cs * 10),
+            "otim" => self.otim = arg(0).parse::<u64>().ok().map(|cs| cs * 10),
+            "undo" => self.undo(1),
+            "remove" => self.undo(2),
+            "memory" => match arg(0).parse::<usize>() {
+                Ok(mb) if (1..=MAX_HASH_MB).contains(&mb) => self.tt = TranspositionTable::new(mb),
+                _ => self.send(&format!("Error (bad arguments): {line}")),
+            },
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "quit" => return false,
+            _ => self.send(&format!("Error (unknown command): {command}")),
+        }
+
+        true
+    }
+
+    /// Take back the last few moves
+    fn undo(&mut self, count: usize) {
+        if self.game.moves.len() < count {
+            self.send("Error (no moves to undo): undo");
+            return;
+        }
+        self.game.moves.truncate(self.game.moves.len() - count);
+        self.pos = self.game.end();
+    }
+
+    /// Play a move for the user, then reply if it's the engine's turn
+    fn usermove(&mut self, movestr: &str) {
+        let Some(mv) = parse_move(&self.pos, movestr) else {
+            self.send(&format!("Illegal move: {movestr}"));
+            return;
+        };
+        self.play(mv);
+
+        if self.engine_side == Some(self.pos.turn) {
+            self.think();
+        }
+    }
+
+    /// Make a move in the game
+    fn play(&mut self, mv: Mv) {
+        let success = self.pos.makemove(&mv);
+        debug_assert!(success);
+        self.game.moves.push(mv);
+    }
+
+    /// Tell the GUI if the game is over
+    /// Returns true if it is
+    fn check_game_over(&mut self) -> bool {
+        if !self.pos.legal_moves().is_empty() {
+            return false;
+        }
+
+        let line = match (self.pos.in_check(), self.pos.turn) {
+            (true, Side::White) => format!("{} {{Black mates}}", GameResult::BlackWin),
+            (true, Side::Black) => format!("{} {{White mates}}", GameResult::WhiteWin),
+            (false, _) => format!("{} {{Stalemate}}", GameResult::Draw),
+        };
+        self.send(&line);
+        true
+    }
+
+    /// Get the search limits from the time control
+    fn limits(&self) -> Limits {
+        let us = self.pos.turn as usize;
+        let them = !self.pos.turn as usize;
+        let mut limits = Limits {
+            movetime: self.movetime,
+            depth: self.depth,
+            ..Default::default()
+        };
+
+        // Without a clock or a time control there's no time limit
+        let base = Some(self.level.base).filter(|base| *base > 0);
+        if self.movetime.is_none() {
+            limits.time[us] = self.time.or(base);
+            limits.time[them] = self.otim.or(base);
+            limits.inc = [Some(self.level.inc); 2];
+            if self.level.mps > 0 {
+                let played = u32::from(self.pos.fullmoves.saturating_sub(1)) % self.level.mps;
+                limits.movestogo = Some(self.level.mps - played);
+            }
+        }
+
+        limits
+    }
+
+    /// Search the current position and play the best move
+    fn think(&mut self) {
+        if self.check_game_over() {
+            return;
+        }
+
+        let pos = self.pos;
+        let limits = self.limits();
+        let post = self.post;
+        let out = &mut self.out;
+        let options = SearchOptions::default();
+        self.signals.abort.store(false, Ordering::Relaxed);
+        self.signals.searching.store(true, Ordering::Relaxed);
+        let bestmove = search(
+            &pos,
+            &limits,
+            &options,
+            &self.signals.stop,
+            &mut self.tt,
+            &mut |report| {
+                // There's no way to show a bound, so only exact scores are posted
+                if post && report.bound == Bound::Exact {
+                    send_line(out, &thinking_line(report));
+                }
+            },
+        );
+
+        // A "?" only applies to the search it arrived during
+        self.signals.searching.store(false, Ordering::Relaxed);
+        self.signals.stop.store(false, Ordering::Relaxed);
+
+        // The game has moved on, so the move no longer applies
+        if self.signals.abort.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        let Some(bestmove) = bestmove else {
+            return;
+        };
+        self.play(bestmove);
+        self.send(&format!("move {bestmove}"));
+        self.check_game_over();
+    }
+}
+
+/// Handle the commands that can't wait for a search, called from the input thread
+/// "?" stops a search so the engine moves now, and "quit" stops it so the engine can exit
+/// Commands that change the game stop it too, and the move found is thrown away
+pub fn intercept(line: &str, signals: &Signals) {
+    let searching = signals.searching.load(Ordering::Relaxed);
+    match line.split_whitespace().next() {
+        Some("?") if searching => signals.stop.store(true, Ordering::Relaxed),
+        Some("new" | "force" | "result" | "setboard" | "undo" | "remove") if searching => {
+            signals.abort.store(true, Ordering::Relaxed);
+            signals.stop.store(true, Ordering::Relaxed);
+        }
+        Some("quit") => signals.stop.store(true, Ordering::Relaxed),
+        _ => {}
+    }
+}
+
+/// Run the XBoard loop until "quit" or the end of the input
+pub fn run<W: Write>(input: impl IntoIterator<Item = String>, out: W, signals: Arc<Signals>) {
+    let mut xboard = XBoard::new(out, signals);
+
+    for line in input {
+        if !xboard.handle(&line) {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        input::{spawn_input, tests::SharedOutput},
+        search::MATE,
+    };
+    use std::{
+        io::BufReader,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    /// Run a script of commands and get the output lines
+    fn run_script(script: &str) -> Vec<String> {
+        let mut out = Vec::new();
+        run(script.lines().map(str::to_string), &mut out, Arc::default());
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_handshake() {
+        let lines = run_script("xboard\nprotover 2\naccepted setboard\nping 7\nquit\nping 8\n");
+        assert_eq!(lines, [FEATURES, "pong 7"]);
+    }
+
+    #[test]
+    fn test_usermove() {
+        let mut xboard = XBoard::new(Vec::new(), Arc::default());
+        xboard.handle("new");
+        xboard.handle("sd 2");
+        xboard.handle("post");
+        xboard.handle("usermove e2e4");
+
+        // The engine plays black and replies straight away, after its thinking output
+        let output = String::from_utf8(xboard.out.clone()).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3, "{output}");
+        assert!(lines[0].starts_with("1 "));
+        assert!(lines[1].starts_with("2 "));
+        assert!(lines[2].starts_with("move "));
+        assert_eq!(xboard.game.moves.len(), 2);
+        assert_eq!(xboard.pos, xboard.game.end());
+
+        // Illegal moves are rejected
+        xboard.out.clear();
+        xboard.handle("usermove e2e4");
+        assert_eq!(
+            String::from_utf8(xboard.out.clone()).unwrap(),
+            "Illegal move: e2e4\n"
+        );
+        assert_eq!(xboard.game.moves.len(), 2);
+    }
+
+    #[test]
+    fn test_force_and_undo() {
+        let mut xboard = XBoard::new(Vec::new(), Arc::default());
+        xboard.handle("new");
+        xboard.handle("force");
+        for movestr in ["e2e4", "e7e5", "g1f3"] {
+            xboard.handle(&format!("usermove {movestr}"));
+        }
+        assert!(xboard.out.is_empty());
+        assert_eq!(
+            xboard.pos.get_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+
+        xboard.handle("undo");
+        assert_eq!(
+            xboard.pos.get_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+        );
+        xboard.handle("remove");
+        assert_eq!(xboard.pos, Position::from_fen("startpos"));
+
+        xboard.handle("undo");
+        assert!(
+            String::from_utf8(xboard.out.clone())
+                .unwrap()
+                .starts_with("Error")
+        );
+
+        // "go" makes the engine play the side to move
+        xboard.out.clear();
+        xboard.handle("sd 1");
+        xboard.handle("go");
+        assert_eq!(xboard.engine_side, Some(Side::White));
+        assert_eq!(xboard.pos.turn, Side::Black);
+    }
+
+    #[test]
+    fn test_setboard() {
+        let mut xboard = XBoard::new(Vec::new(), Arc::default());
+        xboard.handle("setboard 4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+        assert_eq!(xboard.pos.get_fen(), "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+
+        for fen in [
+            "8/8/8/8/8/8/8/8 w - - 0 1",
+            "P3k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - e8 0 1",
+            "4k3/8/8/8/8/8/8/K3R3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w K - 0 1",
+        ] {
+            xboard.handle(&format!("setboard {fen}"));
+            assert_eq!(xboard.pos.get_fen(), "4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+        }
+        assert_eq!(
+            String::from_utf8(xboard.out).unwrap(),
+            "tellusererror Illegal position\n".repeat(5)
+        );
+    }
+
+    #[test]
+    fn test_game_over() {
+        let mut xboard = XBoard::new(Vec::new(), Arc::default());
+        xboard.handle("setboard 7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        xboard.handle("go");
+        assert_eq!(
+            String::from_utf8(xboard.out).unwrap(),
+            "1/2-1/2 {Stalemate}\n"
+        );
+    }
+
+    #[test]
+    fn test_interrupt_search() {
+        let signals = Arc::<Signals>::default();
+        let out = SharedOutput::default();
+        let (reader, mut writer) = std::io::pipe().unwrap();
+
+        let lines = spawn_input(BufReader::new(reader), {
+            let signals = Arc::clone(&signals);
+            move |line| {
+                intercept(line, &signals);
+                true
+            }
+        });
+        let engine = thread::spawn({
+            let signals = Arc::clone(&signals);
+            let out = out.clone();
+            move || run(lines, out, signals)
+        });
+
+        // "?" makes the engine move straight away, however long it has
+        writeln!(writer, "new\npost\nst 1000\ngo").unwrap();
+        out.wait_for("1 ");
+        writeln!(writer, "?").unwrap();
+        out.wait_for("move ");
+
+        // The next search isn't stopped by the old "?"
+        writeln!(writer, "usermove e7e5").unwrap();
+        thread::sleep(Duration::from_millis(200));
+        let moves = || {
+            out.lines()
+                .iter()
+                .filter(|line| line.starts_with("move "))
+                .count()
+        };
+        assert_eq!(moves(), 1);
+
+        // "quit" stops the search too
+        writeln!(writer, "quit").unwrap();
+        engine.join().unwrap();
+        assert_eq!(moves(), 2);
+    }
+
+    #[test]
+    fn test_abort_search() {
+        let signals = Arc::<Signals>::default();
+        let out = SharedOutput::default();
+        let (reader, mut writer) = std::io::pipe().unwrap();
+
+        let lines = spawn_input(BufReader::new(reader), {
+            let signals = Arc::clone(&signals);
+            move |line| {
+                intercept(line, &signals);
+                true
+            }
+        });
+        let engine = thread::spawn({
+            let signals = Arc::clone(&signals);
+            let out = out.clone();
+            move || run(lines, out, signals)
+        });
+
+        // Commands that change the game stop the search without a move being played
+        writeln!(writer, "st 1000").unwrap();
+        for (i, command) in [
+            "new",
+            "force",
+            "result 1-0 {White resigns}",
+            "setboard 4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "undo",
+            "remove",
+        ]
+        .iter()
+        .enumerate()
+        {
+            writeln!(writer, "new\ngo").unwrap();
+            let start = Instant::now();
+            while !signals.searching.load(Ordering::Relaxed) {
+                assert!(start.elapsed() < Duration::from_secs(10), "No search");
+                thread::sleep(Duration::from_millis(1));
+            }
+            writeln!(writer, "{command}\nping {i}").unwrap();
+            out.wait_for(&format!("pong {i}"));
+            assert!(
+                !out.lines().iter().any(|line| line.starts_with("move ")),
+                "{command}"
+            );
+        }
+
+        writeln!(writer, "quit").unwrap();
+        engine.join().unwrap();
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut xboard = XBoard::new(Vec::new(), Arc::default());
+        xboard.handle("new");
+        xboard.handle("level 40 5:30 2");
+        xboard.handle("time 30000");
+        xboard.handle("otim 20000");
+        let limits = xboard.limits();
+        assert_eq!(limits.time, [Some(300_000), Some(200_000)]);
+        assert_eq!(limits.inc, [Some(2000), Some(2000)]);
+        assert_eq!(limits.movestogo, Some(40));
+
+        xboard.handle("sd 6");
+        xboard.handle("st 10");
+        let limits = xboard.limits();
+        assert_eq!(limits.movetime, Some(10_000));
+        assert_eq!(limits.depth, Some(6));
+        assert_eq!(limits.time, [None, None]);
+
+        xboard.handle("level 0 2 0.5");
+        assert_eq!(xboard.limits().inc, [Some(500), Some(500)]);
+        assert_eq!(xboard.limits().movestogo, None);
+
+        // A fullmove number of 0 counts as the first move
+        xboard.handle("setboard 4k3/8/8/8/8/8/8/4K3 w - - 0 0");
+        xboard.handle("level 40 1 0");
+        assert_eq!(xboard.limits().movestogo, Some(40));
+    }
+
+    #[test]
+    fn test_memory() {
+        let mut xboard = XBoard::new(Vec::new(), Arc::default());
+        xboard.handle("memory 1");
+        let small = xboard.tt.capacity();
+        xboard.handle("memory 4");
+        assert_eq!(xboard.tt.capacity(), small * 4);
+
+        xboard.handle("memory 0");
+        assert_eq!(xboard.tt.capacity(), small * 4);
+        assert!(String::from_utf8(xboard.out).unwrap().starts_with("Error"));
+    }
+
+    #[test]
+    fn test_thinking_line() {
+        let report = Report {
+            depth: 4,
+            score: 31,
+            bound: Bound::Exact,
+            nodes: 12345,
+            time: 1230,
+            hashfull: 0,
+            pv: vec![Mv::from_string("d2d4"), Mv::from_string("d7d5")],
+        };
+        assert_eq!(thinking_line(&report), "4 31 123 12345 d2d4 d7d5");
+
+        let report = Report {
+            score: MATE - 5,
+            ..report
+        };
+        assert_eq!(thinking_line(&report), "4 100003 123 12345 d2d4 d7d5");
+        let report = Report {
+            score: -MATE + 4,
+            ..report
+        };
+        assert_eq!(thinking_line(&report), "4 -100002 123 12345 d2d4 d7d5");
+    }
+}